@@ -0,0 +1,250 @@
+use crate::error::Error;
+use crate::simulation::{parallel_simulate, SimulationConfig};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::time::Instant;
+
+/// Version of the baseline file layout written by [`BenchBaseline::new`].
+pub const BASELINE_SCHEMA_VERSION: u32 = 2;
+
+/// Wall times of repeated runs of one configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub threads: u64,
+    /// Trials simulated per run
+    pub samples: u64,
+    pub mean_seconds: f64,
+    pub stddev_seconds: f64,
+    pub min_seconds: f64,
+    pub max_seconds: f64,
+    pub samples_per_second: f64,
+}
+
+impl BenchResult {
+    fn from_times(threads: u64, samples: u64, times: &[f64]) -> BenchResult {
+        let n = times.len() as f64;
+        let mean = times.iter().sum::<f64>() / n;
+        let variance = if times.len() > 1 {
+            times.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        BenchResult {
+            threads,
+            samples,
+            mean_seconds: mean,
+            stddev_seconds: variance.sqrt(),
+            min_seconds: times.iter().copied().fold(f64::INFINITY, f64::min),
+            max_seconds: times.iter().copied().fold(0.0, f64::max),
+            samples_per_second: samples as f64 / mean,
+        }
+    }
+}
+
+/// Times `runs` runs of `config` at every thread count, after `warmup` untimed runs each.
+pub fn benchmark(
+    config: &SimulationConfig,
+    thread_counts: &[u64],
+    warmup: u32,
+    runs: u32,
+) -> Result<Vec<BenchResult>, Error> {
+    if runs == 0 {
+        return Err(Error::InvalidConfig("a benchmark needs at least one run"));
+    }
+    thread_counts
+        .iter()
+        .map(|&threads| {
+            let config = SimulationConfig {
+                num_threads: threads,
+                ..config.clone()
+            };
+            for _ in 0..warmup {
+                parallel_simulate(&config)?;
+            }
+            let mut samples = 0;
+            let times = (0..runs)
+                .map(|_| {
+                    let start_time = Instant::now();
+                    samples = parallel_simulate(&config)?.result.count;
+                    Ok(start_time.elapsed().as_secs_f64())
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            Ok(BenchResult::from_times(threads, samples, &times))
+        })
+        .collect()
+}
+
+/// Writes `results` as the markdown table used in the README, relative to the fastest row.
+pub fn write_bench_table(
+    results: &[BenchResult],
+    program: &str,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let Some(fastest) = results
+        .iter()
+        .min_by(|a, b| a.mean_seconds.total_cmp(&b.mean_seconds))
+    else {
+        return Ok(());
+    };
+
+    writeln!(
+        out,
+        "| Command | Mean [ms] | Min [ms] | Max [ms] | Relative |"
+    )?;
+    writeln!(out, "|:---|---:|---:|---:|---:|")?;
+    for result in results {
+        let relative = result.mean_seconds / fastest.mean_seconds;
+        // Relative errors of the two means add in quadrature
+        let relative_error = relative
+            * ((result.stddev_seconds / result.mean_seconds).powi(2)
+                + (fastest.stddev_seconds / fastest.mean_seconds).powi(2))
+            .sqrt();
+        let relative = if std::ptr::eq(result, fastest) {
+            "1.00".to_string()
+        } else {
+            format!("{:.2} ± {:.2}", relative, relative_error)
+        };
+        writeln!(
+            out,
+            "| `{} -t {}` | {:.1} ± {:.1} | {:.1} | {:.1} | {} |",
+            program,
+            result.threads,
+            result.mean_seconds * 1000.0,
+            result.stddev_seconds * 1000.0,
+            result.min_seconds * 1000.0,
+            result.max_seconds * 1000.0,
+            relative
+        )?;
+    }
+    Ok(())
+}
+
+/// Benchmark results saved with `bench --save-baseline` for later comparison, together with
+/// every setting that changes the throughput.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchBaseline {
+    pub schema_version: u32,
+    pub points: usize,
+    pub kernel: String,
+    pub rng: String,
+    pub distribution: String,
+    pub accumulation: String,
+    pub statistics: Vec<String>,
+    pub histogram_bins: Option<usize>,
+    pub block_size: u64,
+    pub results: Vec<BenchResult>,
+}
+
+impl BenchBaseline {
+    pub fn new(config: &SimulationConfig, results: &[BenchResult]) -> BenchBaseline {
+        BenchBaseline {
+            schema_version: BASELINE_SCHEMA_VERSION,
+            points: config.num_points,
+            kernel: config.kernel.name().to_string(),
+            rng: config.rng_name().to_string(),
+            distribution: config.distribution.to_string(),
+            accumulation: config.accumulation.name().to_string(),
+            statistics: config
+                .statistics
+                .iter()
+                .map(|statistic| statistic.name().to_string())
+                .collect(),
+            histogram_bins: config.histogram_bins,
+            block_size: config.block_size,
+            results: results.to_vec(),
+        }
+    }
+
+    /// Reads a baseline saved by `bench --save-baseline`, rejecting layouts other than
+    /// [`BASELINE_SCHEMA_VERSION`] before they are misread.
+    pub fn read(reader: impl Read) -> io::Result<BenchBaseline> {
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        let version = value["schema_version"].as_u64();
+        if version != Some(u64::from(BASELINE_SCHEMA_VERSION)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unsupported baseline schema version {}, expected {}",
+                    version.map_or_else(|| "(none)".to_string(), |version| version.to_string()),
+                    BASELINE_SCHEMA_VERSION
+                ),
+            ));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Rejects a baseline recorded with other settings, whose throughput says nothing about a
+    /// regression, and one that shares none of `thread_counts`.
+    pub fn check(&self, config: &SimulationConfig, thread_counts: &[u64]) -> Result<(), Error> {
+        let current = BenchBaseline::new(config, &[]);
+        let mismatch = [
+            (self.kernel != current.kernel, "kernel"),
+            (self.points != current.points, "point count"),
+            (self.rng != current.rng, "generator"),
+            (self.distribution != current.distribution, "distribution"),
+            (
+                self.accumulation != current.accumulation,
+                "accumulation mode",
+            ),
+            (self.statistics != current.statistics, "set of statistics"),
+            (
+                self.histogram_bins != current.histogram_bins,
+                "number of histogram bins",
+            ),
+            (self.block_size != current.block_size, "block size"),
+        ]
+        .into_iter()
+        .find_map(|(differs, setting)| differs.then_some(setting));
+        if let Some(setting) = mismatch {
+            return Err(Error::BaselineMismatch(setting));
+        }
+        if !thread_counts
+            .iter()
+            .any(|&threads| self.results.iter().any(|b| b.threads == threads))
+        {
+            return Err(Error::InvalidConfig(
+                "the baseline has none of the benchmarked thread counts",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Throughput change of every result that has a baseline entry with the same thread count,
+    /// once [`BenchBaseline::check`] accepts the baseline.
+    pub fn compare(
+        &self,
+        config: &SimulationConfig,
+        results: &[BenchResult],
+    ) -> Result<Vec<BaselineComparison>, Error> {
+        let thread_counts: Vec<u64> = results.iter().map(|result| result.threads).collect();
+        self.check(config, &thread_counts)?;
+        let comparisons: Vec<BaselineComparison> = results
+            .iter()
+            .filter_map(|result| {
+                let baseline = self.results.iter().find(|b| b.threads == result.threads)?;
+                Some(BaselineComparison {
+                    threads: result.threads,
+                    baseline_samples_per_second: baseline.samples_per_second,
+                    samples_per_second: result.samples_per_second,
+                    change: result.samples_per_second / baseline.samples_per_second - 1.0,
+                })
+            })
+            .collect();
+        Ok(comparisons)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BaselineComparison {
+    pub threads: u64,
+    pub baseline_samples_per_second: f64,
+    pub samples_per_second: f64,
+    /// Relative throughput change; -0.1 is a 10% regression
+    pub change: f64,
+}
+
+impl BaselineComparison {
+    pub fn is_regression(&self, max_regression: f64) -> bool {
+        self.change < -max_regression
+    }
+}
@@ -0,0 +1,649 @@
+use montecarlo::{
+    available_threads, Accumulation, Distribution, Kernel, OutputFormat, Precision, RngKind,
+    SimulationConfig, Statistic, BLOCK_SIZE,
+};
+use rand::prelude::*;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Monte Carlo estimates of the order statistics of n iid points, uniform on [0, 1) by default
+
+Usage: montecarlo [COMMAND] [OPTIONS]
+
+Commands:
+  run       Simulate and report every order statistic (the default)
+  sweep     Run the simulation for a range of point counts
+  theory    Print the theoretical mean and variance of every order statistic
+  scaling   Time one simulation at several thread counts
+  bench     Time repeated runs, optionally against a saved baseline
+  replay    Print the points of one trial of a --rng philox run
+  verify    Test a simulation against theory and fail if any test rejects it
+  help      Print this message
+
+Simulation options (run, sweep, scaling, bench, verify):
+  -s, --simulations N       Trials to run [default: 1e8, unbounded with a target or time limit]
+  -t, --threads N|auto      Worker threads [default: 1]
+      --seed N              Master seed [default: random, always reported]
+  -k, --kernel NAME         scalar, avx2 or avx512 [default: best the CPU supports,
+                            scalar with --rng]
+      --rng NAME            Generator of the scalar kernel: pcg64, pcg64mcg, xoshiro256++,
+                            chacha8, chacha20, std or philox [default: pcg64mcg]
+      --dist LAW            Law of the points: uniform(a,b), normal(mean,sd), exponential(rate),
+                            beta(a,b), triangular(low,mode,high), cauchy(x0,scale) or
+                            lognormal(mu,sigma); bare names take the standard parameters
+                            [default: uniform(0,1)]. beta needs the scalar kernel.
+                            Also taken by theory and replay
+      --stats LIST          Statistics to estimate besides the order statistics, from range,
+                            midrange, distance (mean |X - Y| over pairs), median (odd point
+                            counts) and gaps (between neighbouring points), e.g. range,gaps
+      --block-size N        Trials per work block [default: 1048576]
+      --accumulation MODE   naive or compensated [default: naive]
+
+  -n, --points N            Points per trial [default: 2]; sweep takes a list such as
+                            1..8 or 1,2,4 [default: 1..8]
+  -f, --format FORMAT       text, json or csv, for run, sweep, theory and replay
+                            [default: text]
+
+Run and sweep options:
+      --target-stderr E     Stop once every standard error is at most E
+      --rel-precision R     Stop once every standard error is at most R times its mean
+      --time-limit T        Stop starting new blocks after T, e.g. 500ms, 30s, 2m or 1h
+
+Run and verify options:
+      --histogram BINS      Bin the minimum and maximum of every trial and plot them against
+                            their exact densities, with Kolmogorov-Smirnov and chi-square
+                            tests [verify default: 100]
+      --histogram-csv FILE  Also write the bins, empirical and exact PDF and CDF, as CSV;
+                            run only
+
+Verify options:
+      --alpha P             Chance of failing a correct simulation, split evenly over the
+                            tests [default: 0.001]
+
+Scaling and bench options:
+      --thread-counts LIST  Comma-separated thread counts; 'auto' is allowed
+      --warmup N            Untimed runs per thread count [default: 1]
+      --runs N              Timed runs per thread count [default: 10]
+
+Replay options:
+      --trial N             Index of the trial to replay, counting from 0
+      --seed N              Seed of the run the trial belongs to
+
+Bench options:
+      --save-baseline FILE  Save the results as a baseline
+      --baseline FILE       Compare against a saved baseline and fail on a regression
+      --max-regression P    Tolerated throughput drop in percent [default: 5%]
+
+  -h, --help                Print this message
+  -V, --version             Print the version
+
+Counts accept underscores, scientific notation and the suffixes k, M, G (or B) and T,
+so 1e9, 1_000_000_000, 1000M and 1G are all the same count.";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    // Runs over several point counts
+    Sweep,
+    // Exact moments, no simulation
+    Theory,
+    // Strong-scaling study over several thread counts
+    Scaling,
+    // Repeated timed runs, optionally checked against a saved baseline
+    Bench,
+    // One trial of a counter-based run
+    Replay,
+    // Hypothesis tests of a run against theory
+    Verify,
+    Help,
+    Version,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "run" => Some(Command::Run),
+            "sweep" => Some(Command::Sweep),
+            "theory" => Some(Command::Theory),
+            "scaling" => Some(Command::Scaling),
+            "bench" => Some(Command::Bench),
+            "replay" => Some(Command::Replay),
+            "verify" => Some(Command::Verify),
+            "help" => Some(Command::Help),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Command::Run => "run",
+            Command::Sweep => "sweep",
+            Command::Theory => "theory",
+            Command::Scaling => "scaling",
+            Command::Bench => "bench",
+            Command::Replay => "replay",
+            Command::Verify => "verify",
+            Command::Help => "help",
+            Command::Version => "version",
+        }
+    }
+}
+
+const SIMULATING: &[Command] = &[
+    Command::Run,
+    Command::Sweep,
+    Command::Scaling,
+    Command::Bench,
+    Command::Verify,
+];
+
+pub struct Config {
+    pub command: Command,
+    // None means the default count, or no cap at all for precision-targeted and time-limited runs
+    pub total_simulations: Option<u64>,
+    pub num_threads: u64,
+    pub num_points: usize,
+    // Point counts for the sweep
+    pub point_counts: Vec<usize>,
+    pub seed: Option<u64>,
+    // None means pick the best kernel for the running CPU, or the scalar one if --rng is given
+    pub kernel: Option<Kernel>,
+    pub rng: Option<RngKind>,
+    pub distribution: Distribution,
+    pub statistics: Vec<Statistic>,
+    pub histogram_bins: Option<usize>,
+    pub histogram_csv: Option<String>,
+    // Significance level of verify, over all of its tests
+    pub alpha: f64,
+    // Trial to replay
+    pub trial: u64,
+    pub format: OutputFormat,
+    pub precision: Option<Precision>,
+    pub time_limit: Option<Duration>,
+    pub block_size: u64,
+    pub accumulation: Accumulation,
+    // Thread counts for the scaling study and benchmark; None means each command's default
+    pub thread_counts: Option<Vec<u64>>,
+    pub warmup: u32,
+    pub runs: u32,
+    pub baseline: Option<String>,
+    pub save_baseline: Option<String>,
+    // Largest tolerated throughput drop against the baseline, as a fraction
+    pub max_regression: f64,
+}
+
+impl Config {
+    pub fn simulation_config(&self) -> SimulationConfig {
+        let default_simulations = if self.precision.is_some() || self.time_limit.is_some() {
+            u64::MAX
+        } else {
+            100_000_000
+        };
+        SimulationConfig {
+            total_simulations: self.total_simulations.unwrap_or(default_simulations),
+            num_threads: self.num_threads,
+            num_points: self.num_points,
+            // Without --seed a fresh master seed is drawn, but it is still reported so the run can be replayed
+            seed: self.seed.unwrap_or_else(|| thread_rng().next_u64()),
+            kernel: self.kernel.unwrap_or_else(|| {
+                if self.rng.is_some() || self.distribution.quantile(0.5).is_none() {
+                    Kernel::Scalar
+                } else {
+                    Kernel::detect()
+                }
+            }),
+            rng: self.rng,
+            distribution: self.distribution,
+            statistics: self.statistics.clone(),
+            histogram_bins: self.histogram_bins,
+            precision: self.precision,
+            time_limit: self.time_limit,
+            block_size: self.block_size,
+            accumulation: self.accumulation,
+        }
+    }
+}
+
+fn invalid(flag: &str, text: &str, expected: impl Display) -> String {
+    format!(
+        "invalid value '{}' for {}: expected {}",
+        text, flag, expected
+    )
+}
+
+fn parse_number<T: FromStr>(flag: &str, text: &str, expected: &str) -> Result<T, String> {
+    text.parse().map_err(|_| invalid(flag, text, expected))
+}
+
+// Accepts "1000000", "1_000_000", "1e6", "2.5e6", "1M" or "2.5M"
+fn parse_count(flag: &str, text: &str) -> Result<u64, String> {
+    let digits = text.replace('_', "");
+    let (number, multiplier) = match digits.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&digits[..i], 1e3),
+        Some((i, 'M')) => (&digits[..i], 1e6),
+        Some((i, 'G' | 'B')) => (&digits[..i], 1e9),
+        Some((i, 'T')) => (&digits[..i], 1e12),
+        _ => (digits.as_str(), 1.0),
+    };
+    let expected = "a whole number such as 1000000, 1e6 or 1M";
+    if let Ok(count) = number.parse::<u64>() {
+        return count
+            .checked_mul(multiplier as u64)
+            .ok_or_else(|| invalid(flag, text, "a count that fits in 64 bits"));
+    }
+    // Only plain decimals and exponents; "inf", "nan" and the like are not counts
+    if !number.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return Err(invalid(flag, text, expected));
+    }
+    let count = number
+        .parse::<f64>()
+        .map_err(|_| invalid(flag, text, expected))?
+        * multiplier;
+    if count.fract() != 0.0 {
+        return Err(invalid(flag, text, expected));
+    }
+    if count >= u64::MAX as f64 {
+        return Err(invalid(flag, text, "a count that fits in 64 bits"));
+    }
+    Ok(count as u64)
+}
+
+fn parse_positive_count(flag: &str, text: &str) -> Result<u64, String> {
+    match parse_count(flag, text)? {
+        0 => Err(format!("{} must be at least 1", flag)),
+        count => Ok(count),
+    }
+}
+
+fn parse_positive(flag: &str, text: &str) -> Result<f64, String> {
+    let value = parse_number::<f64>(flag, text, "a positive number")?;
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(invalid(flag, text, "a positive number"))
+    }
+}
+
+// Accepts "30s", "500ms", "2m", "1h" or a bare number of seconds
+fn parse_duration(flag: &str, text: &str) -> Result<Duration, String> {
+    let (number, unit_seconds) = if let Some(number) = text.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = text.strip_suffix('s') {
+        (number, 1.0)
+    } else if let Some(number) = text.strip_suffix('m') {
+        (number, 60.0)
+    } else if let Some(number) = text.strip_suffix('h') {
+        (number, 3600.0)
+    } else {
+        (text, 1.0)
+    };
+    let expected = "a duration such as 500ms, 30s, 2m or 1h";
+    let seconds = number
+        .parse::<f64>()
+        .map_err(|_| invalid(flag, text, expected))?
+        * unit_seconds;
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(duration) if !duration.is_zero() => Ok(duration),
+        _ => Err(invalid(flag, text, expected)),
+    }
+}
+
+// "auto" means every thread this process may run on
+fn parse_threads(flag: &str, text: &str) -> Result<u64, String> {
+    if text == "auto" {
+        return Ok(available_threads());
+    }
+    match parse_number(flag, text, "a positive integer or 'auto'")? {
+        0 => Err(format!("{} must be at least 1", flag)),
+        threads => Ok(threads),
+    }
+}
+
+// Accepts "5", "1..8" (inclusive) or a comma-separated mix such as "1,2,4..6"
+fn parse_point_counts(flag: &str, text: &str) -> Result<Vec<usize>, String> {
+    let mut counts = Vec::new();
+    for part in text.split(',') {
+        match part.split_once("..") {
+            Some((first, last)) => {
+                let first = parse_positive_count(flag, first)? as usize;
+                let last = parse_positive_count(flag, last)? as usize;
+                if first > last {
+                    return Err(invalid(flag, text, "ranges that count upwards"));
+                }
+                counts.extend(first..=last);
+            }
+            None => counts.push(parse_positive_count(flag, part)? as usize),
+        }
+    }
+    Ok(counts)
+}
+
+/// Command-line arguments still to be parsed.
+struct Args {
+    args: VecDeque<String>,
+}
+
+impl Args {
+    /// The value following `flag`.
+    fn value(&mut self, flag: &str) -> Result<String, String> {
+        self.args
+            .pop_front()
+            .ok_or_else(|| format!("{} requires a value", flag))
+    }
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+    let mut args = Args {
+        args: args.into_iter().skip(1).collect(),
+    };
+    let mut command = match args.args.front().map(String::as_str) {
+        Some(name) if !name.starts_with('-') => {
+            let command =
+                Command::from_name(name).ok_or_else(|| format!("unknown command '{}'", name))?;
+            args.args.pop_front();
+            command
+        }
+        _ => Command::Run,
+    };
+    let mut total_simulations = None;
+    let mut num_threads = 1;
+    let mut num_points = 2;
+    let mut point_counts = (1..=8).collect();
+    let mut seed = None;
+    let mut kernel = None;
+    let mut rng = None;
+    let mut trial = None;
+    let mut distribution = Distribution::default();
+    let mut statistics = Vec::new();
+    let mut histogram_bins = None;
+    let mut histogram_csv = None;
+    let mut alpha = 0.001;
+    let mut format = OutputFormat::Text;
+    let mut precision = None;
+    let mut time_limit = None;
+    let mut block_size = BLOCK_SIZE;
+    let mut accumulation = Accumulation::Naive;
+    let mut thread_counts = None;
+    let mut warmup = 1;
+    let mut runs = 10;
+    let mut baseline = None;
+    let mut save_baseline = None;
+    let mut max_regression = 0.05;
+
+    while let Some(arg) = args.args.pop_front() {
+        // "--flag=value" is the same as "--flag value"
+        let flag = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                args.args.push_front(value.to_string());
+                flag.to_string()
+            }
+            _ => arg,
+        };
+        let flag = flag.as_str();
+        let allowed = |commands: &[Command]| {
+            if commands.contains(&command) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} does not apply to the {} command",
+                    flag,
+                    command.name()
+                ))
+            }
+        };
+        match flag {
+            // Everything after these is ignored, as with most tools
+            "-h" | "--help" => {
+                command = Command::Help;
+                break;
+            }
+            "-V" | "--version" => {
+                command = Command::Version;
+                break;
+            }
+            "-s" | "--simulations" => {
+                allowed(SIMULATING)?;
+                total_simulations = Some(parse_positive_count(flag, &args.value(flag)?)?);
+            }
+            "-t" | "--threads" => {
+                allowed(SIMULATING)?;
+                num_threads = parse_threads(flag, &args.value(flag)?)?;
+            }
+            "-n" | "--points" if command == Command::Sweep => {
+                point_counts = parse_point_counts(flag, &args.value(flag)?)?;
+            }
+            "-n" | "--points" => {
+                num_points = parse_positive_count(flag, &args.value(flag)?)? as usize;
+            }
+            "--seed" => {
+                allowed(&[SIMULATING, &[Command::Replay]].concat())?;
+                let value = args.value(flag)?;
+                seed = Some(parse_number(flag, &value, "an unsigned 64-bit integer")?);
+            }
+            "-k" | "--kernel" => {
+                allowed(SIMULATING)?;
+                let value = args.value(flag)?;
+                kernel = Some(
+                    Kernel::from_name(&value)
+                        .ok_or_else(|| invalid(flag, &value, "scalar, avx2 or avx512"))?,
+                );
+            }
+            "--rng" => {
+                allowed(SIMULATING)?;
+                let value = args.value(flag)?;
+                rng = Some(RngKind::from_name(&value).ok_or_else(|| {
+                    invalid(
+                        flag,
+                        &value,
+                        "pcg64, pcg64mcg, xoshiro256++, chacha8, chacha20, std or philox",
+                    )
+                })?);
+            }
+            "--dist" => {
+                allowed(&[SIMULATING, &[Command::Theory, Command::Replay]].concat())?;
+                let value = args.value(flag)?;
+                distribution = Distribution::from_spec(&value)
+                    .map_err(|err| format!("invalid value '{}' for {}: {}", value, flag, err))?;
+            }
+            "--stats" => {
+                allowed(SIMULATING)?;
+                let value = args.value(flag)?;
+                statistics.clear();
+                for name in value.split(',') {
+                    let statistic = Statistic::from_name(name).ok_or_else(|| {
+                        invalid(
+                            flag,
+                            &value,
+                            "a list of range, midrange, distance, median and gaps",
+                        )
+                    })?;
+                    if !statistics.contains(&statistic) {
+                        statistics.push(statistic);
+                    }
+                }
+            }
+            "--histogram" => {
+                allowed(&[Command::Run, Command::Verify])?;
+                histogram_bins = Some(parse_positive_count(flag, &args.value(flag)?)? as usize);
+            }
+            "--histogram-csv" => {
+                allowed(&[Command::Run])?;
+                histogram_csv = Some(args.value(flag)?);
+            }
+            "-f" | "--format" => {
+                allowed(&[
+                    Command::Run,
+                    Command::Sweep,
+                    Command::Theory,
+                    Command::Replay,
+                ])?;
+                let value = args.value(flag)?;
+                format = OutputFormat::from_name(&value)
+                    .ok_or_else(|| invalid(flag, &value, "text, json or csv"))?;
+            }
+            "--target-stderr" => {
+                allowed(&[Command::Run, Command::Sweep])?;
+                precision = Some(Precision::StdError(parse_positive(
+                    flag,
+                    &args.value(flag)?,
+                )?));
+            }
+            "--rel-precision" => {
+                allowed(&[Command::Run, Command::Sweep])?;
+                precision = Some(Precision::Relative(parse_positive(
+                    flag,
+                    &args.value(flag)?,
+                )?));
+            }
+            "--time-limit" => {
+                allowed(&[Command::Run, Command::Sweep])?;
+                time_limit = Some(parse_duration(flag, &args.value(flag)?)?);
+            }
+            "--block-size" => {
+                allowed(SIMULATING)?;
+                block_size = parse_positive_count(flag, &args.value(flag)?)?;
+            }
+            "--accumulation" => {
+                allowed(SIMULATING)?;
+                let value = args.value(flag)?;
+                accumulation = Accumulation::from_name(&value)
+                    .ok_or_else(|| invalid(flag, &value, "naive or compensated"))?;
+            }
+            "--thread-counts" => {
+                allowed(&[Command::Scaling, Command::Bench])?;
+                thread_counts = Some(
+                    args.value(flag)?
+                        .split(',')
+                        .map(|text| parse_threads(flag, text))
+                        .collect::<Result<Vec<_>, _>>()?,
+                );
+            }
+            "--warmup" => {
+                allowed(&[Command::Scaling, Command::Bench])?;
+                let value = args.value(flag)?;
+                warmup = parse_number(flag, &value, "a number of runs")?;
+            }
+            "--runs" => {
+                allowed(&[Command::Scaling, Command::Bench])?;
+                let value = args.value(flag)?;
+                runs = parse_number(flag, &value, "a number of runs")?;
+                if runs == 0 {
+                    return Err(format!("{} must be at least 1", flag));
+                }
+            }
+            "--baseline" => {
+                allowed(&[Command::Bench])?;
+                baseline = Some(args.value(flag)?);
+            }
+            "--save-baseline" => {
+                allowed(&[Command::Bench])?;
+                save_baseline = Some(args.value(flag)?);
+            }
+            "--max-regression" => {
+                allowed(&[Command::Bench])?;
+                // A percentage, with or without the trailing "%"
+                let value = args.value(flag)?;
+                max_regression = value
+                    .trim_end_matches('%')
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|percent| *percent >= 0.0)
+                    .ok_or_else(|| invalid(flag, &value, "a percentage such as 5%"))?
+                    / 100.0;
+            }
+            "--alpha" => {
+                allowed(&[Command::Verify])?;
+                let value = args.value(flag)?;
+                alpha = parse_number::<f64>(flag, &value, "a probability between 0 and 1")?;
+                if !(alpha > 0.0 && alpha < 1.0) {
+                    return Err(invalid(flag, &value, "a probability between 0 and 1"));
+                }
+            }
+            "--trial" => {
+                allowed(&[Command::Replay])?;
+                trial = Some(parse_count(flag, &args.value(flag)?)?);
+            }
+            _ if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            _ => return Err(format!("unexpected argument '{}'", flag)),
+        }
+    }
+
+    if command == Command::Replay {
+        if seed.is_none() {
+            return Err("replay needs the --seed of the run".to_string());
+        }
+        if trial.is_none() {
+            return Err("replay needs the --trial to print".to_string());
+        }
+        rng = Some(RngKind::Philox);
+    }
+    if command == Command::Verify && histogram_bins.is_none() {
+        histogram_bins = Some(100);
+    }
+    if histogram_csv.is_some() && histogram_bins.is_none() {
+        return Err("--histogram-csv needs --histogram".to_string());
+    }
+    // A sweep only reports the statistics that apply to each point count
+    if command != Command::Sweep {
+        if let Some(statistic) = statistics
+            .iter()
+            .find(|statistic| !statistic.applies_to(num_points))
+        {
+            return Err(match statistic {
+                Statistic::Median => format!(
+                    "--stats median needs an odd number of points, not {}",
+                    num_points
+                ),
+                _ => format!("--stats {} needs at least 2 points", statistic.name()),
+            });
+        }
+    }
+    if let Some(kernel) = kernel {
+        if kernel != Kernel::Scalar && distribution.quantile(0.5).is_none() {
+            return Err(format!(
+                "{} has no closed-form quantile, so it needs the scalar kernel, not {}",
+                distribution,
+                kernel.name()
+            ));
+        }
+    }
+    if let (Some(_), Some(kernel)) = (rng, kernel) {
+        if kernel != Kernel::Scalar {
+            return Err(format!(
+                "--rng needs the scalar kernel; the {} kernel has its own xoshiro256+ streams",
+                kernel.name()
+            ));
+        }
+    }
+
+    Ok(Config {
+        command,
+        total_simulations,
+        num_threads,
+        num_points,
+        point_counts,
+        seed,
+        kernel,
+        rng,
+        trial: trial.unwrap_or(0),
+        distribution,
+        statistics,
+        histogram_bins,
+        histogram_csv,
+        alpha,
+        format,
+        precision,
+        time_limit,
+        block_size,
+        accumulation,
+        thread_counts,
+        warmup,
+        runs,
+        baseline,
+        save_baseline,
+        max_regression,
+    })
+}
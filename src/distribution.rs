@@ -0,0 +1,466 @@
+use crate::error::Error;
+use crate::special::{incomplete_beta, incomplete_gamma, ln_beta, ln_power};
+use rand::Rng;
+use rand_distr::{Beta, Cauchy, Distribution as _, Exp, LogNormal, Normal, Triangular};
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Law the points of every trial are drawn from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    Uniform { low: f64, high: f64 },
+    Normal { mean: f64, std_dev: f64 },
+    Exponential { rate: f64 },
+    Beta { alpha: f64, beta: f64 },
+    Triangular { low: f64, mode: f64, high: f64 },
+    Cauchy { location: f64, scale: f64 },
+    LogNormal { mu: f64, sigma: f64 },
+}
+
+impl Default for Distribution {
+    fn default() -> Self {
+        Distribution::Uniform {
+            low: 0.0,
+            high: 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Distribution::Uniform { low, high } => write!(f, "uniform({}, {})", low, high),
+            Distribution::Normal { mean, std_dev } => write!(f, "normal({}, {})", mean, std_dev),
+            Distribution::Exponential { rate } => write!(f, "exponential({})", rate),
+            Distribution::Beta { alpha, beta } => write!(f, "beta({}, {})", alpha, beta),
+            Distribution::Triangular { low, mode, high } => {
+                write!(f, "triangular({}, {}, {})", low, mode, high)
+            }
+            Distribution::Cauchy { location, scale } => {
+                write!(f, "cauchy({}, {})", location, scale)
+            }
+            Distribution::LogNormal { mu, sigma } => write!(f, "lognormal({}, {})", mu, sigma),
+        }
+    }
+}
+
+impl Distribution {
+    /// Parses specs such as `normal(0, 1)`, `beta(2,5)` or `exponential`; a bare name means
+    /// the standard parameters, except for beta, which has none.
+    pub fn from_spec(spec: &str) -> Result<Distribution, &'static str> {
+        let spec = spec.trim();
+        let (name, params) = match spec.split_once('(') {
+            Some((name, rest)) => {
+                let params = rest.strip_suffix(')').ok_or("missing ')'")?;
+                let params = params
+                    .split(',')
+                    .map(|param| param.trim().parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| "parameters must be numbers")?;
+                (name.trim(), Some(params))
+            }
+            None => (spec, None),
+        };
+        let with_defaults = |defaults: &[f64]| -> Result<Vec<f64>, &'static str> {
+            match &params {
+                None => Ok(defaults.to_vec()),
+                Some(params) if params.len() == defaults.len() => Ok(params.clone()),
+                Some(_) => Err("wrong number of parameters"),
+            }
+        };
+        let distribution = match name {
+            "uniform" => {
+                let p = with_defaults(&[0.0, 1.0])?;
+                Distribution::Uniform {
+                    low: p[0],
+                    high: p[1],
+                }
+            }
+            "normal" => {
+                let p = with_defaults(&[0.0, 1.0])?;
+                Distribution::Normal {
+                    mean: p[0],
+                    std_dev: p[1],
+                }
+            }
+            "exponential" => Distribution::Exponential {
+                rate: with_defaults(&[1.0])?[0],
+            },
+            "beta" => {
+                let p = match &params {
+                    Some(p) if p.len() == 2 => p,
+                    _ => return Err("beta needs two parameters"),
+                };
+                Distribution::Beta {
+                    alpha: p[0],
+                    beta: p[1],
+                }
+            }
+            "triangular" => {
+                let p = with_defaults(&[0.0, 0.5, 1.0])?;
+                Distribution::Triangular {
+                    low: p[0],
+                    mode: p[1],
+                    high: p[2],
+                }
+            }
+            "cauchy" => {
+                let p = with_defaults(&[0.0, 1.0])?;
+                Distribution::Cauchy {
+                    location: p[0],
+                    scale: p[1],
+                }
+            }
+            "lognormal" => {
+                let p = with_defaults(&[0.0, 1.0])?;
+                Distribution::LogNormal {
+                    mu: p[0],
+                    sigma: p[1],
+                }
+            }
+            _ => return Err("unknown distribution"),
+        };
+        distribution
+            .check()
+            .map(|_| distribution)
+            .map_err(|_| "parameters out of range")
+    }
+
+    pub fn check(&self) -> Result<(), Error> {
+        let finite = |values: &[f64]| values.iter().all(|value| value.is_finite());
+        let valid = match *self {
+            Distribution::Uniform { low, high } => finite(&[low, high]) && low < high,
+            Distribution::Normal { mean, std_dev } => finite(&[mean, std_dev]) && std_dev > 0.0,
+            Distribution::Exponential { rate } => finite(&[rate]) && rate > 0.0,
+            Distribution::Beta { alpha, beta } => {
+                finite(&[alpha, beta]) && alpha > 0.0 && beta > 0.0
+            }
+            Distribution::Triangular { low, mode, high } => {
+                finite(&[low, mode, high]) && low <= mode && mode <= high && low < high
+            }
+            Distribution::Cauchy { location, scale } => finite(&[location, scale]) && scale > 0.0,
+            Distribution::LogNormal { mu, sigma } => finite(&[mu, sigma]) && sigma > 0.0,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig("distribution parameters out of range"))
+        }
+    }
+
+    /// Uniform on [0, 1), the law whose order statistics the SIMD kernels produce directly.
+    pub fn is_standard_uniform(&self) -> bool {
+        *self == Distribution::default()
+    }
+
+    /// Inverse CDF at `p`, or `None` for beta, which has no closed form.
+    ///
+    /// Except for uniform laws, `p` is moved to the middle of its 2^-52 wide cell, so the
+    /// uniforms of the SIMD kernels, which lie on [0, 1), never reach an infinite tail.
+    pub fn quantile(&self, p: f64) -> Option<f64> {
+        let p = match self {
+            Distribution::Uniform { .. } => p,
+            _ => p + f64::EPSILON / 2.0,
+        };
+        self.inverse_cdf(p, 1.0 - p)
+    }
+
+    /// Inverse CDF at `p`, given `q = 1 - p` as well, so both tails keep full precision.
+    pub(crate) fn inverse_cdf(&self, p: f64, q: f64) -> Option<f64> {
+        let lower = p <= 0.5;
+        let x = match *self {
+            Distribution::Uniform { low, high } if lower => low + (high - low) * p,
+            Distribution::Uniform { low, high } => high - (high - low) * q,
+            Distribution::Normal { mean, std_dev } => mean + std_dev * normal_inverse(p, q),
+            Distribution::Exponential { rate } if lower => -(-p).ln_1p() / rate,
+            Distribution::Exponential { rate } => -q.ln() / rate,
+            Distribution::Beta { .. } => return None,
+            Distribution::Triangular { low, mode, high } => {
+                let width = high - low;
+                if p < (mode - low) / width {
+                    low + (p * width * (mode - low)).sqrt()
+                } else {
+                    high - (q * width * (high - mode)).sqrt()
+                }
+            }
+            Distribution::Cauchy { location, scale } if lower => location - scale / (PI * p).tan(),
+            Distribution::Cauchy { location, scale } => location + scale / (PI * q).tan(),
+            Distribution::LogNormal { mu, sigma } => (mu + sigma * normal_inverse(p, q)).exp(),
+        };
+        Some(x)
+    }
+
+    /// A value in the bulk of the law and the scale of its spread, as `(centre, spread)`.
+    pub fn location_scale(&self) -> (f64, f64) {
+        match *self {
+            Distribution::Uniform { low, high } | Distribution::Triangular { low, high, .. } => {
+                ((low + high) / 2.0, high - low)
+            }
+            Distribution::Normal { mean, std_dev } => (mean, std_dev),
+            Distribution::Exponential { rate } => (1.0 / rate, 1.0 / rate),
+            Distribution::Beta { alpha, beta } => {
+                let total = alpha + beta;
+                (
+                    alpha / total,
+                    (alpha * beta / (total * total * (total + 1.0))).sqrt(),
+                )
+            }
+            Distribution::Cauchy { location, scale } => (location, scale),
+            Distribution::LogNormal { mu, sigma } => (mu.exp(), mu.exp() * sigma),
+        }
+    }
+
+    /// Smallest and largest values the law can take, infinite where it is unbounded.
+    pub fn support(&self) -> (f64, f64) {
+        match *self {
+            Distribution::Uniform { low, high } => (low, high),
+            Distribution::Triangular { low, high, .. } => (low, high),
+            Distribution::Beta { .. } => (0.0, 1.0),
+            Distribution::Exponential { .. } | Distribution::LogNormal { .. } => {
+                (0.0, f64::INFINITY)
+            }
+            Distribution::Normal { .. } | Distribution::Cauchy { .. } => {
+                (f64::NEG_INFINITY, f64::INFINITY)
+            }
+        }
+    }
+
+    /// Probability density at `x`, zero outside the support.
+    pub fn density(&self, x: f64) -> f64 {
+        let (low, high) = self.support();
+        if x < low || x > high {
+            return 0.0;
+        }
+        let normal = |z: f64| (-z * z / 2.0).exp() / (2.0 * PI).sqrt();
+        match *self {
+            Distribution::Uniform { low, high } => 1.0 / (high - low),
+            Distribution::Normal { mean, std_dev } => normal((x - mean) / std_dev) / std_dev,
+            Distribution::Exponential { rate } => rate * (-rate * x).exp(),
+            Distribution::Beta { alpha, beta } => {
+                (ln_power(x, alpha - 1.0) + ln_power(1.0 - x, beta - 1.0) - ln_beta(alpha, beta))
+                    .exp()
+            }
+            Distribution::Triangular { low, mode, high } => {
+                let width = high - low;
+                if x < mode || mode == high {
+                    2.0 * (x - low) / (width * (mode - low))
+                } else {
+                    2.0 * (high - x) / (width * (high - mode))
+                }
+            }
+            Distribution::Cauchy { location, scale } => {
+                let t = (x - location) / scale;
+                1.0 / (PI * scale * (1.0 + t * t))
+            }
+            Distribution::LogNormal { .. } if x == 0.0 => 0.0,
+            Distribution::LogNormal { mu, sigma } => normal((x.ln() - mu) / sigma) / (x * sigma),
+        }
+    }
+
+    pub fn cdf(&self, x: f64) -> f64 {
+        self.cdf_pair(x).0
+    }
+
+    /// CDF and survival function at `x`, each to full relative precision in its own tail.
+    pub(crate) fn cdf_pair(&self, x: f64) -> (f64, f64) {
+        let complement = |p: f64| (p, 1.0 - p);
+        match *self {
+            Distribution::Uniform { low, high } => {
+                let width = high - low;
+                let p = ((x - low) / width).clamp(0.0, 1.0);
+                if p <= 0.5 {
+                    complement(p)
+                } else {
+                    let q = ((high - x) / width).clamp(0.0, 1.0);
+                    (1.0 - q, q)
+                }
+            }
+            Distribution::Normal { mean, std_dev } => normal_cdf_pair((x - mean) / std_dev),
+            Distribution::Exponential { .. } if x <= 0.0 => (0.0, 1.0),
+            Distribution::Exponential { rate } => (-(-rate * x).exp_m1(), (-rate * x).exp()),
+            Distribution::Beta { .. } if x <= 0.0 => (0.0, 1.0),
+            Distribution::Beta { .. } if x >= 1.0 => (1.0, 0.0),
+            Distribution::Beta { alpha, beta } => incomplete_beta(alpha, beta, x, 1.0 - x),
+            Distribution::Triangular { low, .. } if x <= low => (0.0, 1.0),
+            Distribution::Triangular { high, .. } if x >= high => (1.0, 0.0),
+            Distribution::Triangular { low, mode, high } => {
+                let width = high - low;
+                if x < mode {
+                    complement((x - low) * (x - low) / (width * (mode - low)))
+                } else {
+                    let q = (high - x) * (high - x) / (width * (high - mode));
+                    (1.0 - q, q)
+                }
+            }
+            Distribution::Cauchy { location, scale } => {
+                let t = (x - location) / scale;
+                ((1.0_f64).atan2(-t) / PI, (1.0_f64).atan2(t) / PI)
+            }
+            Distribution::LogNormal { .. } if x <= 0.0 => (0.0, 1.0),
+            Distribution::LogNormal { mu, sigma } => normal_cdf_pair((x.ln() - mu) / sigma),
+        }
+    }
+
+    /// Sampler for the scalar kernel; panics if the parameters fail [`Distribution::check`].
+    pub(crate) fn sampler(&self) -> Sampler {
+        let invalid = "distribution parameters are checked before sampling";
+        match *self {
+            Distribution::Uniform { low, high } => Sampler::Uniform {
+                low,
+                width: high - low,
+            },
+            Distribution::Normal { mean, std_dev } => {
+                Sampler::Normal(Normal::new(mean, std_dev).expect(invalid))
+            }
+            Distribution::Exponential { rate } => {
+                Sampler::Exponential(Exp::new(rate).expect(invalid))
+            }
+            Distribution::Beta { alpha, beta } => {
+                Sampler::Beta(Beta::new(alpha, beta).expect(invalid))
+            }
+            Distribution::Triangular { low, mode, high } => {
+                Sampler::Triangular(Triangular::new(low, high, mode).expect(invalid))
+            }
+            Distribution::Cauchy { location, scale } => {
+                Sampler::Cauchy(Cauchy::new(location, scale).expect(invalid))
+            }
+            Distribution::LogNormal { mu, sigma } => {
+                Sampler::LogNormal(LogNormal::new(mu, sigma).expect(invalid))
+            }
+        }
+    }
+}
+
+/// A [`Distribution`] with its `rand_distr` sampler set up once per block.
+pub(crate) enum Sampler {
+    // Scaled by hand so the standard uniform keeps drawing exactly `rng.gen::<f64>()`
+    Uniform { low: f64, width: f64 },
+    Normal(Normal<f64>),
+    Exponential(Exp<f64>),
+    Beta(Beta<f64>),
+    Triangular(Triangular<f64>),
+    Cauchy(Cauchy<f64>),
+    LogNormal(LogNormal<f64>),
+}
+
+impl Sampler {
+    #[inline]
+    pub(crate) fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match self {
+            Sampler::Uniform { low, width } => low + width * rng.gen::<f64>(),
+            Sampler::Normal(normal) => normal.sample(rng),
+            Sampler::Exponential(exp) => exp.sample(rng),
+            Sampler::Beta(beta) => beta.sample(rng),
+            Sampler::Triangular(triangular) => triangular.sample(rng),
+            Sampler::Cauchy(cauchy) => cauchy.sample(rng),
+            Sampler::LogNormal(log_normal) => log_normal.sample(rng),
+        }
+    }
+}
+
+/// Standard normal CDF and survival function at `z`, through `Q(1/2, z^2 / 2) = erfc(|z| / sqrt(2))`.
+fn normal_cdf_pair(z: f64) -> (f64, f64) {
+    let tail = incomplete_gamma(0.5, z * z / 2.0).1 / 2.0;
+    if z < 0.0 {
+        (tail, 1.0 - tail)
+    } else {
+        (1.0 - tail, tail)
+    }
+}
+
+// Either tail through the lower one, which AS 241 evaluates from `p` itself
+fn normal_inverse(p: f64, q: f64) -> f64 {
+    if p <= 0.5 {
+        normal_quantile(p)
+    } else {
+        -normal_quantile(q)
+    }
+}
+
+/// Standard normal quantile by Wichura's algorithm AS 241, accurate to about 1e-16.
+// The coefficients are quoted exactly as published
+#[allow(clippy::excessive_precision)]
+pub fn normal_quantile(p: f64) -> f64 {
+    fn poly(coefficients: &[f64], x: f64) -> f64 {
+        coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c)
+    }
+    const A: [f64; 8] = [
+        3.3871328727963666080e0,
+        1.3314166789178437745e2,
+        1.9715909503065514427e3,
+        1.3731693765509461125e4,
+        4.5921953931549871457e4,
+        6.7265770927008700853e4,
+        3.3430575583588128105e4,
+        2.5090809287301226727e3,
+    ];
+    const B: [f64; 8] = [
+        1.0,
+        4.2313330701600911252e1,
+        6.8718700749205790830e2,
+        5.3941960214247511077e3,
+        2.1213794301586595867e4,
+        3.9307895800092710610e4,
+        2.8729085735721942674e4,
+        5.2264952788528545610e3,
+    ];
+    const C: [f64; 8] = [
+        1.42343711074968357734e0,
+        4.63033784615654529590e0,
+        5.76949722146069140550e0,
+        3.64784832476320460504e0,
+        1.27045825245236838258e0,
+        2.41780725177450611770e-1,
+        2.27238449892691845833e-2,
+        7.74545014278341407640e-4,
+    ];
+    const D: [f64; 8] = [
+        1.0,
+        2.05319162663775882187e0,
+        1.67638483018380384940e0,
+        6.89767334985100004550e-1,
+        1.48103976427480074590e-1,
+        1.51986665636164571966e-2,
+        5.47593808499534494600e-4,
+        1.05075007164441684324e-9,
+    ];
+    const E: [f64; 8] = [
+        6.65790464350110377720e0,
+        5.46378491116411436990e0,
+        1.78482653991729133580e0,
+        2.96560571828504891230e-1,
+        2.65321895265761230930e-2,
+        1.24266094738807843860e-3,
+        2.71155556874348757815e-5,
+        2.01033439929228813265e-7,
+    ];
+    const F: [f64; 8] = [
+        1.0,
+        5.99832206555887937690e-1,
+        1.36929880922735805310e-1,
+        1.48753612908506148525e-2,
+        7.86869131145613259100e-4,
+        1.84631831751005468180e-5,
+        1.42151175831644588870e-7,
+        2.04426310338993978564e-15,
+    ];
+
+    let q = p - 0.5;
+    if q.abs() <= 0.425 {
+        let r = 0.180_625 - q * q;
+        return q * poly(&A, r) / poly(&B, r);
+    }
+    let r = (-(if q < 0.0 { p } else { 1.0 - p }).ln()).sqrt();
+    let x = if r <= 5.0 {
+        let r = r - 1.6;
+        poly(&C, r) / poly(&D, r)
+    } else {
+        let r = r - 5.0;
+        poly(&E, r) / poly(&F, r)
+    };
+    if q < 0.0 {
+        -x
+    } else {
+        x
+    }
+}
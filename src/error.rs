@@ -0,0 +1,39 @@
+use crate::kernel::Kernel;
+use rayon::ThreadPoolBuildError;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    UnsupportedKernel(Kernel),
+    InvalidConfig(&'static str),
+    /// A bench baseline recorded with another value of this setting
+    BaselineMismatch(&'static str),
+    ThreadPool(ThreadPoolBuildError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedKernel(kernel) => {
+                write!(
+                    f,
+                    "the {} kernel is not supported on this CPU",
+                    kernel.name()
+                )
+            }
+            Error::InvalidConfig(message) => write!(f, "invalid configuration: {}", message),
+            Error::BaselineMismatch(setting) => {
+                write!(f, "the baseline was recorded with another {}", setting)
+            }
+            Error::ThreadPool(err) => write!(f, "failed to start the worker pool: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ThreadPoolBuildError> for Error {
+    fn from(err: ThreadPoolBuildError) -> Self {
+        Error::ThreadPool(err)
+    }
+}
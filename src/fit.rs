@@ -0,0 +1,230 @@
+//! Goodness-of-fit tests of simulated statistics against their exact laws.
+
+use crate::histogram::Histogram;
+use crate::report::Report;
+use crate::special::incomplete_gamma;
+use crate::statistic::Measure;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Expected count below which the chi-square test pools a cell with its neighbours
+const MIN_EXPECTED: f64 = 5.0;
+
+/// Kolmogorov-Smirnov test of binned values: the largest distance between the empirical and
+/// the exact CDF over the bin edges.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct KolmogorovSmirnov {
+    pub statistic: f64,
+    /// Only the edges are compared, so this errs on the high side
+    pub p_value: f64,
+}
+
+impl Check {
+    pub fn passes(&self, threshold: f64) -> bool {
+        self.p_value >= threshold
+    }
+}
+
+/// The level each of `num_tests` tests is held to so that a correct simulation fails any of them
+/// with probability at most `alpha` (Bonferroni).
+pub fn bonferroni_threshold(alpha: f64, num_tests: usize) -> f64 {
+    alpha / num_tests as f64
+}
+
+/// Writes the `verify` command's table of `checks`, each held to the Bonferroni threshold.
+pub fn write_verification_table(
+    checks: &[Check],
+    alpha: f64,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let threshold = bonferroni_threshold(alpha, checks.len());
+    writeln!(
+        out,
+        "{} tests at significance level {} ({:.3e} each):\n",
+        checks.len(),
+        alpha,
+        threshold
+    )?;
+    writeln!(out, "| Statistic | Test | Value | p-value | Result |")?;
+    writeln!(out, "|:---|:---|---:|---:|:---|")?;
+    for check in checks {
+        writeln!(
+            out,
+            "| {} | {} | {:.6} | {:.4e} | {} |",
+            check.label,
+            check.test,
+            check.statistic,
+            check.p_value,
+            if check.passes(threshold) {
+                "pass"
+            } else {
+                "FAIL"
+            }
+        )?;
+    }
+    Ok(())
+}
+
+impl KolmogorovSmirnov {
+    /// `edge_cdf` holds the exact CDF at `histogram.low` and at the upper edge of every bin.
+    pub fn new(histogram: &Histogram, edge_cdf: &[f64]) -> KolmogorovSmirnov {
+        let total = histogram.total() as f64;
+        let mut cumulative = histogram.below;
+        let mut statistic = (cumulative as f64 / total - edge_cdf[0]).abs();
+        for (count, cdf) in histogram.counts.iter().zip(&edge_cdf[1..]) {
+            cumulative += count;
+            statistic = statistic.max((cumulative as f64 / total - cdf).abs());
+        }
+        KolmogorovSmirnov {
+            statistic,
+            p_value: kolmogorov_p_value(statistic, histogram.total()),
+        }
+    }
+}
+
+/// Pearson's chi-square test of the bin counts, including the counts on either side of the
+/// binned range.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ChiSquare {
+    pub statistic: f64,
+    pub degrees_of_freedom: usize,
+    pub p_value: f64,
+}
+
+impl ChiSquare {
+    /// Cells expecting fewer than 5 values are pooled with their neighbours first. `None` if
+    /// fewer than two cells remain; `edge_cdf` is as for [`KolmogorovSmirnov::new`].
+    pub fn new(histogram: &Histogram, edge_cdf: &[f64]) -> Option<ChiSquare> {
+        let total = histogram.total() as f64;
+        let observed = std::iter::once(histogram.below)
+            .chain(histogram.counts.iter().copied())
+            .chain(std::iter::once(histogram.above));
+        let probabilities = std::iter::once(edge_cdf[0])
+            .chain(edge_cdf.windows(2).map(|pair| pair[1] - pair[0]))
+            .chain(std::iter::once(1.0 - edge_cdf[edge_cdf.len() - 1]));
+
+        let mut cells: Vec<(f64, f64)> = Vec::new();
+        let mut pending = (0.0, 0.0);
+        for (count, probability) in observed.zip(probabilities) {
+            pending = (pending.0 + count as f64, pending.1 + total * probability);
+            if pending.1 >= MIN_EXPECTED {
+                cells.push(pending);
+                pending = (0.0, 0.0);
+            }
+        }
+        // Whatever is left over joins the last full cell
+        match cells.last_mut() {
+            Some(last) => *last = (last.0 + pending.0, last.1 + pending.1),
+            None => cells.push(pending),
+        }
+        if cells.len() < 2 {
+            return None;
+        }
+
+        let statistic = cells
+            .iter()
+            .map(|(observed, expected)| (observed - expected) * (observed - expected) / expected)
+            .sum();
+        let degrees_of_freedom = cells.len() - 1;
+        Some(ChiSquare {
+            statistic,
+            degrees_of_freedom,
+            p_value: chi_square_p_value(statistic, degrees_of_freedom),
+        })
+    }
+}
+
+/// `P(chi^2 >= statistic)` for the given degrees of freedom.
+pub fn chi_square_p_value(statistic: f64, degrees_of_freedom: usize) -> f64 {
+    incomplete_gamma(degrees_of_freedom as f64 / 2.0, statistic / 2.0).1
+}
+
+/// `P(D >= statistic)` for the Kolmogorov-Smirnov distance of `count` values, by the
+/// asymptotic Kolmogorov distribution with Stephens' small-sample correction.
+pub fn kolmogorov_p_value(statistic: f64, count: u64) -> f64 {
+    let root = (count as f64).sqrt();
+    let lambda = (root + 0.12 + 0.11 / root) * statistic;
+    // The series below needs many terms here, and the p-value is within 1e-12 of 1 anyway
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    // 2 sum over j >= 1 of (-1)^(j-1) exp(-2 j^2 lambda^2)
+    let mut sum = 0.0;
+    for j in 1..=100 {
+        let term = (-2.0 * (j * j) as f64 * lambda * lambda).exp();
+        sum += if j % 2 == 1 { term } else { -term };
+        if term < 1e-16 * sum {
+            break;
+        }
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+/// Two-sided `P(|Z| >= |z|)` for a standard normal Z.
+pub fn normal_p_value(z: f64) -> f64 {
+    incomplete_gamma(0.5, z * z / 2.0).1
+}
+
+/// One hypothesis test of a run against theory.
+#[derive(Clone, Debug, Serialize)]
+pub struct Check {
+    pub label: String,
+    /// `"mean"`, `"kolmogorov_smirnov"` or `"chi_square"`
+    pub test: &'static str,
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+/// Every test [`Report`] has the exact values for: a z-test of each mean whose variance is
+/// finite, and both goodness-of-fit tests of each histogram. `measures` are those of the run,
+/// in the order of its statistics.
+pub fn verification_checks(report: &Report, measures: &[Measure]) -> Vec<Check> {
+    let num_points = report.points;
+    // Cauchy points lack a variance, and so do their order statistics next to the extremes
+    let finite_variance = |k: usize| {
+        report.statistics[k - 1]
+            .theoretical_variance
+            .is_some_and(f64::is_finite)
+    };
+    let mut checks: Vec<Check> = report
+        .statistics
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| {
+            if i < num_points {
+                finite_variance(i + 1)
+            } else {
+                measures[i - num_points]
+                    .weights(num_points)
+                    .iter()
+                    .all(|&(index, _)| finite_variance(index + 1))
+            }
+        })
+        .filter_map(|(_, stat)| {
+            let z_score = stat.z_score?;
+            Some(Check {
+                label: stat.label.clone(),
+                test: "mean",
+                statistic: z_score,
+                p_value: normal_p_value(z_score),
+            })
+        })
+        .collect();
+    for histogram in &report.histograms {
+        checks.push(Check {
+            label: histogram.label.clone(),
+            test: "kolmogorov_smirnov",
+            statistic: histogram.kolmogorov_smirnov.statistic,
+            p_value: histogram.kolmogorov_smirnov.p_value,
+        });
+        if let Some(chi_square) = histogram.chi_square {
+            checks.push(Check {
+                label: histogram.label.clone(),
+                test: "chi_square",
+                statistic: chi_square.statistic,
+                p_value: chi_square.p_value,
+            });
+        }
+    }
+    checks
+}
@@ -0,0 +1,250 @@
+use crate::distribution::Distribution;
+use crate::fit::{ChiSquare, KolmogorovSmirnov};
+use crate::report::order_statistic_label;
+use crate::theory::order_statistic_cdf;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Fixed-width bins over [low, high), with counters for the values that fall outside.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub low: f64,
+    pub high: f64,
+    pub counts: Vec<u64>,
+    pub below: u64,
+    pub above: u64,
+    // Bins per unit, so binning multiplies instead of divides
+    scale: f64,
+}
+
+impl Histogram {
+    pub fn new(low: f64, high: f64, bins: usize) -> Histogram {
+        Histogram {
+            low,
+            high,
+            counts: vec![0; bins],
+            below: 0,
+            above: 0,
+            scale: bins as f64 / (high - low),
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, value: f64) {
+        let position = (value - self.low) * self.scale;
+        if position < 0.0 {
+            self.below += 1;
+        } else {
+            match self.counts.get_mut(position as usize) {
+                Some(count) => *count += 1,
+                None => self.above += 1,
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.below += other.below;
+        self.above += other.above;
+    }
+
+    /// Values binned, including those outside the range.
+    pub fn total(&self) -> u64 {
+        self.below + self.above + self.counts.iter().sum::<u64>()
+    }
+
+    pub fn bin_width(&self) -> f64 {
+        (self.high - self.low) / self.counts.len() as f64
+    }
+}
+
+/// Histograms of every trial's minimum and maximum, over one range shared by both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtremeHistograms {
+    pub minimum: Histogram,
+    pub maximum: Histogram,
+}
+
+impl ExtremeHistograms {
+    /// The range is the law's support where it is bounded. Unbounded ends stop where only one
+    /// trial in a thousand has its minimum below, or its maximum above, the range.
+    pub fn new(distribution: &Distribution, num_points: usize, bins: usize) -> ExtremeHistograms {
+        let (mut low, mut high) = distribution.support();
+        // P(min <= x) = 1 - (1 - F(x))^n, so that is 1/1000 where F(x) = 1 - 0.999^(1/n)
+        let tail = -((-1e-3_f64).ln_1p() / num_points as f64).exp_m1();
+        if low == f64::NEG_INFINITY {
+            low = distribution.inverse_cdf(tail, 1.0 - tail).unwrap_or(low);
+        }
+        if high == f64::INFINITY {
+            high = distribution.inverse_cdf(1.0 - tail, tail).unwrap_or(high);
+        }
+        ExtremeHistograms {
+            minimum: Histogram::new(low, high, bins),
+            maximum: Histogram::new(low, high, bins),
+        }
+    }
+
+    #[inline]
+    pub fn add(&mut self, minimum: f64, maximum: f64) {
+        self.minimum.add(minimum);
+        self.maximum.add(maximum);
+    }
+
+    pub fn merge(&mut self, other: &ExtremeHistograms) {
+        self.minimum.merge(&other.minimum);
+        self.maximum.merge(&other.maximum);
+    }
+}
+
+/// One bin of a histogram next to the exact law of its statistic.
+#[derive(Clone, Debug, Serialize)]
+pub struct BinReport {
+    pub low: f64,
+    pub high: f64,
+    pub count: u64,
+    /// Fraction of the trials in the bin divided by its width
+    pub density: f64,
+    /// Fraction of the trials at or below `high`
+    pub cdf: f64,
+    /// Exact probability of the bin divided by its width, the value `density` estimates
+    pub theoretical_density: f64,
+    pub theoretical_cdf: f64,
+}
+
+/// Empirical law of one extreme, in the shape serialized by `--format json`.
+#[derive(Clone, Debug, Serialize)]
+pub struct HistogramReport {
+    pub label: String,
+    /// Trials outside the binned range, on either side
+    pub below: u64,
+    pub above: u64,
+    pub bins: Vec<BinReport>,
+    pub kolmogorov_smirnov: KolmogorovSmirnov,
+    /// Absent if the trials are too few to fill two cells
+    pub chi_square: Option<ChiSquare>,
+}
+
+impl HistogramReport {
+    /// Compares `histogram`, which binned the `k`-th smallest point, with its exact law.
+    pub fn new(
+        histogram: &Histogram,
+        distribution: &Distribution,
+        k: usize,
+        num_points: usize,
+    ) -> HistogramReport {
+        let total = histogram.total() as f64;
+        let width = histogram.bin_width();
+        let edge = |bin: usize| histogram.low + bin as f64 * width;
+        // Exact CDF at every bin edge, from histogram.low up
+        let edge_cdf: Vec<f64> = (0..=histogram.counts.len())
+            .map(|bin| order_statistic_cdf(distribution, k, num_points, edge(bin)))
+            .collect();
+        let mut cumulative = histogram.below;
+        let bins = histogram
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(bin, &count)| {
+                cumulative += count;
+                BinReport {
+                    low: edge(bin),
+                    high: edge(bin + 1),
+                    count,
+                    density: count as f64 / (total * width),
+                    cdf: cumulative as f64 / total,
+                    theoretical_density: (edge_cdf[bin + 1] - edge_cdf[bin]) / width,
+                    theoretical_cdf: edge_cdf[bin + 1],
+                }
+            })
+            .collect();
+        HistogramReport {
+            label: order_statistic_label(k, num_points),
+            below: histogram.below,
+            above: histogram.above,
+            bins,
+            kolmogorov_smirnov: KolmogorovSmirnov::new(histogram, &edge_cdf),
+            chi_square: ChiSquare::new(histogram, &edge_cdf),
+        }
+    }
+
+    /// Bar chart of the empirical density, with the exact density of every bin marked, followed
+    /// by the goodness-of-fit tests.
+    pub fn write_plot(&self, out: &mut impl Write) -> io::Result<()> {
+        const WIDTH: usize = 50;
+        let peak = self
+            .bins
+            .iter()
+            .map(|bin| bin.density.max(bin.theoretical_density))
+            .fold(0.0, f64::max);
+        let columns = |density: f64| (density / peak * WIDTH as f64).round() as usize;
+
+        writeln!(
+            out,
+            "\nHistogram of the {} (# simulated, * exact density):",
+            self.label
+        )?;
+        writeln!(out, "{:>12} {:>10} {:>10}", "Bin start", "Density", "Exact")?;
+        for bin in &self.bins {
+            let mut bar = vec![' '; WIDTH + 1];
+            bar[..columns(bin.density)].fill('#');
+            bar[columns(bin.theoretical_density)] = '*';
+            let bar: String = bar.into_iter().collect();
+            writeln!(
+                out,
+                "{:>12.4} {:>10.4} {:>10.4} |{}",
+                bin.low,
+                bin.density,
+                bin.theoretical_density,
+                bar.trim_end()
+            )?;
+        }
+        if self.below + self.above > 0 {
+            writeln!(
+                out,
+                "{} trial(s) below and {} above the binned range",
+                self.below, self.above
+            )?;
+        }
+        writeln!(
+            out,
+            "Kolmogorov-Smirnov D = {:.6} (p = {:.4})",
+            self.kolmogorov_smirnov.statistic, self.kolmogorov_smirnov.p_value
+        )?;
+        match self.chi_square {
+            Some(chi_square) => writeln!(
+                out,
+                "Chi-square = {:.3} on {} degrees of freedom (p = {:.4})",
+                chi_square.statistic, chi_square.degrees_of_freedom, chi_square.p_value
+            ),
+            None => writeln!(out, "Chi-square: too few trials"),
+        }
+    }
+}
+
+/// Writes `reports` as one CSV table, a row per bin.
+pub fn write_histogram_csv(reports: &[HistogramReport], out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "label,bin,low,high,count,density,cdf,theoretical_density,theoretical_cdf"
+    )?;
+    for report in reports {
+        for (index, bin) in report.bins.iter().enumerate() {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{}",
+                report.label,
+                index,
+                bin.low,
+                bin.high,
+                bin.count,
+                bin.density,
+                bin.cdf,
+                bin.theoretical_density,
+                bin.theoretical_cdf
+            )?;
+        }
+    }
+    Ok(())
+}
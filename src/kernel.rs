@@ -0,0 +1,227 @@
+use crate::distribution::Sampler;
+use crate::result::{Accumulation, SimulationResult};
+use crate::rng::{Philox4x32, RngKind};
+#[cfg(target_arch = "x86_64")]
+use crate::simd::{simulate_points_avx2, simulate_points_avx512};
+use crate::simulation::SimulationConfig;
+use rand::prelude::*;
+use rand_chacha::{ChaCha20Rng, ChaCha8Rng};
+use rand_pcg::{Pcg64, Pcg64Mcg};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::ops::Range;
+
+fn simulate_trial<R: RngCore>(rng: &mut R, sampler: &Sampler, points: &mut [f64]) {
+    // Insertion sort while drawing; n is small, so this beats a general sort
+    for filled in 0..points.len() {
+        let point = sampler.sample(rng);
+        let mut slot = filled;
+        while slot > 0 && points[slot - 1] > point {
+            points[slot] = points[slot - 1];
+            slot -= 1;
+        }
+        points[slot] = point;
+    }
+}
+
+fn simulate_points_philox(
+    mut rng: Philox4x32,
+    trials: Range<u64>,
+    num_points: usize,
+    mut result: SimulationResult,
+    sampler: Sampler,
+    accumulation: Accumulation,
+) -> SimulationResult {
+    let mut points = vec![0.0; num_points];
+    for trial in trials {
+        // Every trial starts at its own counter, which is what makes replay_trial possible
+        rng.seek_trial(trial);
+        simulate_trial(&mut rng, &sampler, &mut points);
+        result.add_trial(&points, accumulation);
+    }
+
+    result
+}
+
+fn simulate_points_scalar<R: RngCore>(
+    mut rng: R,
+    num_simulations: u64,
+    num_points: usize,
+    mut result: SimulationResult,
+    sampler: Sampler,
+    accumulation: Accumulation,
+) -> SimulationResult {
+    let mut points = vec![0.0; num_points];
+    for _ in 0..num_simulations {
+        simulate_trial(&mut rng, &sampler, &mut points);
+        result.add_trial(&points, accumulation);
+    }
+
+    result
+}
+
+/// Runs block `block` on the scalar kernel with a stream of `R` seeded from the block's seeds.
+fn run_scalar<R: SeedableRng + RngCore>(config: &SimulationConfig, block: u64) -> SimulationResult {
+    let trials = config.block_trials(block);
+    simulate_points_scalar(
+        config.block_seeds(block).seed_rng::<R>(),
+        trials.end - trials.start,
+        config.num_points,
+        config.empty_result(),
+        config.distribution.sampler(),
+        config.accumulation,
+    )
+}
+
+/// Simulation kernel; the SIMD variants need the matching CPU feature at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    Scalar,
+    Avx2,
+    Avx512,
+}
+
+impl Kernel {
+    pub fn from_name(name: &str) -> Option<Kernel> {
+        match name {
+            "scalar" => Some(Kernel::Scalar),
+            "avx2" => Some(Kernel::Avx2),
+            "avx512" => Some(Kernel::Avx512),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kernel::Scalar => "scalar",
+            Kernel::Avx2 => "avx2",
+            Kernel::Avx512 => "avx512",
+        }
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            Kernel::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(not(target_arch = "x86_64"))]
+            Kernel::Avx2 | Kernel::Avx512 => false,
+        }
+    }
+
+    /// Fastest kernel the running CPU supports.
+    pub fn detect() -> Kernel {
+        if Kernel::Avx512.is_supported() {
+            Kernel::Avx512
+        } else if Kernel::Avx2.is_supported() {
+            Kernel::Avx2
+        } else {
+            Kernel::Scalar
+        }
+    }
+
+    /// Name of the generator the kernel draws from when no [`RngKind`] is selected.
+    pub fn native_rng_name(self) -> &'static str {
+        match self {
+            Kernel::Scalar => RngKind::default().name(),
+            Kernel::Avx2 => "xoshiro256+x4",
+            Kernel::Avx512 => "xoshiro256+x8",
+        }
+    }
+
+    /// Runs block `block` of the simulation `config` describes.
+    ///
+    /// Panics if the running CPU does not support this kernel, see [`Kernel::is_supported`], or
+    /// if `config.rng` is given to a SIMD kernel.
+    pub fn simulate(self, config: &SimulationConfig, block: u64) -> SimulationResult {
+        // The SIMD kernels are UB on CPUs without the feature, so this check is what keeps them sound
+        assert!(
+            self.is_supported(),
+            "the {} kernel is not supported on this CPU",
+            self.name()
+        );
+        assert!(
+            config.rng.is_none() || self == Kernel::Scalar,
+            "the {} kernel has its own generator",
+            self.name()
+        );
+        assert!(
+            self == Kernel::Scalar || config.distribution.quantile(0.5).is_some(),
+            "the {} kernel needs a distribution with a quantile function",
+            self.name()
+        );
+        let SimulationConfig {
+            num_points,
+            rng,
+            ref distribution,
+            accumulation,
+            ..
+        } = *config;
+        let trials = config.block_trials(block);
+        #[cfg(target_arch = "x86_64")]
+        let num_simulations = trials.end - trials.start;
+        #[cfg(target_arch = "x86_64")]
+        let seeds = &config.block_seeds(block);
+        match self {
+            Kernel::Scalar => match rng.unwrap_or_default() {
+                RngKind::Pcg64 => run_scalar::<Pcg64>(config, block),
+                RngKind::Pcg64Mcg => run_scalar::<Pcg64Mcg>(config, block),
+                RngKind::Xoshiro256PlusPlus => run_scalar::<Xoshiro256PlusPlus>(config, block),
+                RngKind::ChaCha8 => run_scalar::<ChaCha8Rng>(config, block),
+                RngKind::ChaCha20 => run_scalar::<ChaCha20Rng>(config, block),
+                RngKind::StdRng => run_scalar::<StdRng>(config, block),
+                RngKind::Philox => simulate_points_philox(
+                    config.philox(),
+                    trials,
+                    num_points,
+                    config.empty_result(),
+                    distribution.sampler(),
+                    accumulation,
+                ),
+            },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe {
+                match accumulation {
+                    Accumulation::Naive => simulate_points_avx2::<false>(
+                        num_simulations,
+                        num_points,
+                        config.empty_result(),
+                        seeds,
+                        distribution,
+                    ),
+                    Accumulation::Compensated => simulate_points_avx2::<true>(
+                        num_simulations,
+                        num_points,
+                        config.empty_result(),
+                        seeds,
+                        distribution,
+                    ),
+                }
+            },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx512 => unsafe {
+                match accumulation {
+                    Accumulation::Naive => simulate_points_avx512::<false>(
+                        num_simulations,
+                        num_points,
+                        config.empty_result(),
+                        seeds,
+                        distribution,
+                    ),
+                    Accumulation::Compensated => simulate_points_avx512::<true>(
+                        num_simulations,
+                        num_points,
+                        config.empty_result(),
+                        seeds,
+                        distribution,
+                    ),
+                }
+            },
+            #[cfg(not(target_arch = "x86_64"))]
+            Kernel::Avx2 | Kernel::Avx512 => {
+                unreachable!("{} kernel selected on a non-x86_64 target", self.name())
+            }
+        }
+    }
+}
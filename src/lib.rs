@@ -0,0 +1,51 @@
+//! Monte Carlo estimation of the order statistics of n iid points, uniform on [0, 1) by default.
+//!
+//! [`parallel_simulate`] splits a run into fixed-size blocks, each with its own RNG stream
+//! derived from the master seed, and reduces them in block order so results are reproducible.
+
+pub mod bench;
+pub mod distribution;
+pub mod error;
+pub mod fit;
+pub mod histogram;
+pub mod kernel;
+pub mod report;
+pub mod result;
+pub mod rng;
+pub mod scaling;
+#[cfg(target_arch = "x86_64")]
+mod simd;
+pub mod simulation;
+mod special;
+pub mod statistic;
+pub mod theory;
+
+pub use bench::{benchmark, write_bench_table, BaselineComparison, BenchBaseline, BenchResult};
+pub use distribution::{normal_quantile, Distribution};
+pub use error::Error;
+pub use fit::{
+    bonferroni_threshold, chi_square_p_value, kolmogorov_p_value, normal_p_value,
+    verification_checks, write_verification_table, Check, ChiSquare, KolmogorovSmirnov,
+};
+pub use histogram::{
+    write_histogram_csv, BinReport, ExtremeHistograms, Histogram, HistogramReport,
+};
+pub use kernel::Kernel;
+pub use report::{
+    order_statistic_label, theory_rows, write_sweep, write_theory, OutputFormat, ReplayedTrial,
+    Report, TheoryRow,
+};
+pub use result::{Accumulation, CompensatedSum, Estimate, SimulationResult, Z_95, Z_99};
+pub use rng::{Philox4x32, RngKind, SeedSequence};
+pub use scaling::{
+    default_thread_counts, scaling_rows, scaling_study, write_scaling_table, ScalingRow,
+};
+pub use simulation::{
+    available_threads, parallel_simulate, replay_trial, Precision, SimulationConfig, SimulationRun,
+    WorkerStats, BLOCK_SIZE,
+};
+pub use statistic::{Measure, Statistic};
+pub use theory::{
+    expected_measure, expected_order_statistic, measure_variance, order_statistic_cdf,
+    order_statistic_density, order_statistic_moments, uniform_order_statistic, Moments,
+};
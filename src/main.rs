@@ -1,157 +1,290 @@
-use rand::prelude::*;
-use rand_pcg::Pcg64Mcg;
-use std::arch::x86_64::*;
+mod cli;
+
+use cli::{parse_args, Command, Config, USAGE};
+use montecarlo::{
+    available_threads, benchmark, bonferroni_threshold, default_thread_counts, parallel_simulate,
+    replay_trial, scaling_study, theory_rows, verification_checks, write_bench_table,
+    write_histogram_csv, write_scaling_table, write_sweep, write_theory, write_verification_table,
+    BenchBaseline, OutputFormat, ReplayedTrial, Report, SimulationConfig,
+};
 use std::env;
-use std::thread;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::process;
 use std::time::Instant;
 
-#[derive(Default)]
-struct SimulationResult {
-    min_sum: f64,
-    max_sum: f64,
+fn exit_with_error(err: impl Display) -> ! {
+    eprintln!("Error: {}", err);
+    process::exit(1);
 }
 
-#[target_feature(enable = "avx2")]
-unsafe fn simulate_points_avx2(num_simulations: u64, seed: u64) -> SimulationResult {
-    let mut rng = Pcg64Mcg::new(seed as u128);
-    let mut result = SimulationResult::default();
-
-    let iterations = num_simulations / 4;
-    let remainder = num_simulations % 4;
+// Bad command lines exit with 2, like most command-line tools
+fn exit_with_usage_error(err: impl Display) -> ! {
+    eprintln!("Error: {}", err);
+    eprintln!("\nRun 'montecarlo --help' for usage.");
+    process::exit(2);
+}
 
-    let mut min_sum = _mm256_setzero_pd();
-    let mut max_sum = _mm256_setzero_pd();
+// As invoked, so the tables show the command that reproduces them
+fn program_name() -> String {
+    env::args()
+        .next()
+        .unwrap_or_else(|| "montecarlo".to_string())
+}
 
-    for _ in 0..iterations {
-        let r1: f64 = rng.gen();
-        let r2: f64 = rng.gen();
-        let r3: f64 = rng.gen();
-        let r4: f64 = rng.gen();
-        let r5: f64 = rng.gen();
-        let r6: f64 = rng.gen();
-        let r7: f64 = rng.gen();
-        let r8: f64 = rng.gen();
+fn run(config: &Config) {
+    let simulation = config.simulation_config();
 
-        let vec1 = _mm256_set_pd(r1, r2, r3, r4);
-        let vec2 = _mm256_set_pd(r5, r6, r7, r8);
+    if config.format == OutputFormat::Text {
+        match (config.precision, config.time_limit) {
+            (Some(_), _) => println!(
+                "Running simulations of {} point(s) with {} thread(s) until the target precision is reached...",
+                simulation.num_points, simulation.num_threads
+            ),
+            (None, Some(limit)) => println!(
+                "Running simulations of {} point(s) with {} thread(s) for {:.2} seconds...",
+                simulation.num_points,
+                simulation.num_threads,
+                limit.as_secs_f64()
+            ),
+            (None, None) => println!(
+                "Running {} simulations of {} point(s) with {} thread(s)...",
+                simulation.total_simulations, simulation.num_points, simulation.num_threads
+            ),
+        }
+    }
 
-        let min_vec = _mm256_min_pd(vec1, vec2);
-        let max_vec = _mm256_max_pd(vec1, vec2);
+    let start_time = Instant::now();
 
-        min_sum = _mm256_add_pd(min_sum, min_vec);
-        max_sum = _mm256_add_pd(max_sum, max_vec);
-    }
+    let result = parallel_simulate(&simulation).unwrap_or_else(|err| exit_with_error(err));
 
-    let mut min_array = [0.0; 4];
-    let mut max_array = [0.0; 4];
-    _mm256_storeu_pd(min_array.as_mut_ptr(), min_sum);
-    _mm256_storeu_pd(max_array.as_mut_ptr(), max_sum);
+    let elapsed_time = start_time.elapsed();
 
-    result.min_sum = min_array.iter().sum();
-    result.max_sum = max_array.iter().sum();
+    let report = Report::new(&simulation, &result, elapsed_time);
+    report
+        .write(config.format, &mut io::stdout().lock())
+        .unwrap_or_else(|err| exit_with_error(err));
 
-    // Handle remaining simulations
-    for _ in 0..remainder {
-        let point1: f64 = rng.gen();
-        let point2: f64 = rng.gen();
-        result.min_sum += point1.min(point2);
-        result.max_sum += point1.max(point2);
+    if let Some(path) = &config.histogram_csv {
+        let file = File::create(path).unwrap_or_else(|err| exit_with_error(err));
+        let mut out = BufWriter::new(file);
+        write_histogram_csv(&report.histograms, &mut out)
+            .and_then(|_| out.flush())
+            .unwrap_or_else(|err| exit_with_error(err));
+        if config.format == OutputFormat::Text {
+            println!("\nSaved histograms to {}", path);
+        }
     }
-
-    result
 }
 
-fn parallel_simulate(total_simulations: u64, num_threads: u64) -> (f64, f64) {
-    let chunk_size = total_simulations / num_threads;
-    let remainder = total_simulations % num_threads;
+fn sweep(config: &Config) {
+    let simulation = config.simulation_config();
+    if config.format == OutputFormat::Text {
+        println!(
+            "Sweeping {} point count(s) with seed {} on the {} kernel...\n",
+            config.point_counts.len(),
+            simulation.seed,
+            simulation.kernel.name()
+        );
+    }
 
-    let results: Vec<_> = (0..num_threads)
-        .map(|i| {
-            let simulations = if i == num_threads - 1 {
-                chunk_size + remainder
-            } else {
-                chunk_size
+    // Every point count reuses the master seed, so a sweep replays like a single run
+    let reports: Vec<Report> = config
+        .point_counts
+        .iter()
+        .map(|&num_points| {
+            let simulation = SimulationConfig {
+                num_points,
+                statistics: simulation
+                    .statistics
+                    .iter()
+                    .copied()
+                    .filter(|statistic| statistic.applies_to(num_points))
+                    .collect(),
+                ..simulation.clone()
             };
-            let seed = thread_rng().next_u64();
-            thread::spawn(move || unsafe { simulate_points_avx2(simulations, seed) })
+            let start_time = Instant::now();
+            let result = parallel_simulate(&simulation).unwrap_or_else(|err| exit_with_error(err));
+            Report::new(&simulation, &result, start_time.elapsed())
         })
-        .collect::<Vec<_>>()
-        .into_iter()
-        .map(|h| h.join().unwrap())
         .collect();
 
-    let total_result = results
-        .iter()
-        .fold(SimulationResult::default(), |mut acc, res| {
-            acc.min_sum += res.min_sum;
-            acc.max_sum += res.max_sum;
-            acc
-        });
-
-    (
-        total_result.min_sum / total_simulations as f64,
-        total_result.max_sum / total_simulations as f64,
-    )
+    write_sweep(&reports, config.format, &mut io::stdout().lock())
+        .unwrap_or_else(|err| exit_with_error(err));
 }
 
-fn parse_args() -> (u64, u64) {
-    let args: Vec<String> = env::args().collect();
-    let mut total_simulations = 100_000_000;
-    let mut num_threads = 1;
-
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "-s" | "--simulations" => {
-                if i + 1 < args.len() {
-                    total_simulations = args[i + 1].parse().unwrap_or(100_000_000);
-                    i += 1;
-                }
-            }
-            "-t" | "--threads" => {
-                if i + 1 < args.len() {
-                    num_threads = args[i + 1].parse().unwrap_or(1);
-                    i += 1;
-                }
-            }
-            _ => {}
-        }
-        i += 1;
-    }
+fn theory(config: &Config) {
+    let rows = theory_rows(&config.distribution, config.num_points);
+    write_theory(&rows, config.format, &mut io::stdout().lock())
+        .unwrap_or_else(|err| exit_with_error(err));
+}
 
-    (total_simulations, num_threads)
+fn replay(config: &Config) {
+    let simulation = config.simulation_config();
+    let points = replay_trial(&simulation, config.trial).unwrap_or_else(|err| exit_with_error(err));
+    ReplayedTrial::new(simulation.seed, config.trial, points)
+        .write(config.format, &mut io::stdout().lock())
+        .unwrap_or_else(|err| exit_with_error(err));
 }
 
-fn main() {
-    let (total_simulations, num_threads) = parse_args();
+fn scaling(config: &Config) {
+    let simulation = config.simulation_config();
+    let thread_counts = config
+        .thread_counts
+        .clone()
+        .unwrap_or_else(|| default_thread_counts(available_threads()));
 
     println!(
-        "Running {} simulations with {} thread(s)...",
-        total_simulations, num_threads
+        "Strong scaling of {} simulations of {} point(s) on the {} kernel ({} warmup, {} runs)...\n",
+        simulation.total_simulations,
+        simulation.num_points,
+        simulation.kernel.name(),
+        config.warmup,
+        config.runs
     );
 
-    let start_time = Instant::now();
+    let rows = scaling_study(&simulation, &thread_counts, config.warmup, config.runs)
+        .unwrap_or_else(|err| exit_with_error(err));
+    write_scaling_table(&rows, &program_name(), &mut io::stdout().lock())
+        .unwrap_or_else(|err| exit_with_error(err));
+}
 
-    let (expected_min, expected_max) = parallel_simulate(total_simulations, num_threads);
+fn bench(config: &Config) {
+    let simulation = config.simulation_config();
+    let thread_counts = config
+        .thread_counts
+        .clone()
+        .unwrap_or_else(|| vec![simulation.num_threads]);
 
-    let elapsed_time = start_time.elapsed();
-
-    println!(
-        "\nSimulation completed in {:.2} seconds",
-        elapsed_time.as_secs_f64()
-    );
-    println!("Number of simulations: {}", total_simulations);
-    println!("Number of threads: {}", num_threads);
-    println!("Expected value of minimum point: {:.8}", expected_min);
-    println!("Expected value of maximum point: {:.8}", expected_max);
-    println!("\nTheoretical expected value of minimum: {:.8}", 1.0 / 3.0);
-    println!("Theoretical expected value of maximum: {:.8}", 2.0 / 3.0);
     println!(
-        "Difference from theoretical (min): {:.8}",
-        (expected_min - 1.0 / 3.0).abs()
+        "Benchmarking {} simulations of {} point(s) on the {} kernel ({} warmup, {} runs)...\n",
+        simulation.total_simulations,
+        simulation.num_points,
+        simulation.kernel.name(),
+        config.warmup,
+        config.runs
     );
+
+    // Before the runs, so a baseline that cannot be compared fails fast
+    let baseline = config.baseline.as_ref().map(|path| {
+        let file = File::open(path).unwrap_or_else(|err| exit_with_error(err));
+        let baseline = BenchBaseline::read(BufReader::new(file))
+            .unwrap_or_else(|err| exit_with_error(format!("{}: {}", path, err)));
+        baseline
+            .check(&simulation, &thread_counts)
+            .unwrap_or_else(|err| exit_with_usage_error(err));
+        (path, baseline)
+    });
+
+    let results = benchmark(&simulation, &thread_counts, config.warmup, config.runs)
+        .unwrap_or_else(|err| exit_with_error(err));
+
+    for result in &results {
+        println!(
+            "-t {}: {:.1} ms ± {:.1} ms (min {:.1} ms, max {:.1} ms), {:.1} M samples/s",
+            result.threads,
+            result.mean_seconds * 1000.0,
+            result.stddev_seconds * 1000.0,
+            result.min_seconds * 1000.0,
+            result.max_seconds * 1000.0,
+            result.samples_per_second / 1e6
+        );
+    }
+    println!();
+    write_bench_table(&results, &program_name(), &mut io::stdout().lock())
+        .unwrap_or_else(|err| exit_with_error(err));
+
+    if let Some(path) = &config.save_baseline {
+        let file = File::create(path).unwrap_or_else(|err| exit_with_error(err));
+        let mut out = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut out, &BenchBaseline::new(&simulation, &results))
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(out))
+            .unwrap_or_else(|err| exit_with_error(err));
+        println!("\nSaved baseline to {}", path);
+    }
+
+    if let Some((path, baseline)) = &baseline {
+        println!(
+            "\nComparison with {} (max regression {:.1}%):",
+            path,
+            config.max_regression * 100.0
+        );
+        let comparisons = baseline
+            .compare(&simulation, &results)
+            .unwrap_or_else(|err| exit_with_error(err));
+        let mut regressed = false;
+        for result in &results {
+            let Some(comparison) = comparisons
+                .iter()
+                .find(|comparison| comparison.threads == result.threads)
+            else {
+                println!("  -t {}: not in the baseline", result.threads);
+                continue;
+            };
+            let is_regression = comparison.is_regression(config.max_regression);
+            regressed |= is_regression;
+            println!(
+                "  -t {}: {:.1} -> {:.1} M samples/s ({:+.1}%){}",
+                comparison.threads,
+                comparison.baseline_samples_per_second / 1e6,
+                comparison.samples_per_second / 1e6,
+                comparison.change * 100.0,
+                if is_regression { "  REGRESSION" } else { "" }
+            );
+        }
+        if regressed {
+            exit_with_error("throughput regressed beyond the allowed threshold");
+        }
+    }
+}
+
+fn verify(config: &Config) {
+    let simulation = config.simulation_config();
     println!(
-        "Difference from theoretical (max): {:.8}",
-        (expected_max - 2.0 / 3.0).abs()
+        "Verifying {} simulations of {} point(s) from {} on the {} kernel with seed {}...",
+        simulation.total_simulations,
+        simulation.num_points,
+        simulation.distribution,
+        simulation.kernel.name(),
+        simulation.seed
     );
+
+    let start_time = Instant::now();
+    let result = parallel_simulate(&simulation).unwrap_or_else(|err| exit_with_error(err));
+    let report = Report::new(&simulation, &result, start_time.elapsed());
+    let checks = verification_checks(&report, &result.result.measures);
+
+    println!();
+    write_verification_table(&checks, config.alpha, &mut io::stdout().lock())
+        .unwrap_or_else(|err| exit_with_error(err));
+    let threshold = bonferroni_threshold(config.alpha, checks.len());
+    let failures = checks
+        .iter()
+        .filter(|check| !check.passes(threshold))
+        .count();
+    if failures > 0 {
+        exit_with_error(format!(
+            "{} of {} tests rejected the simulation",
+            failures,
+            checks.len()
+        ));
+    }
+    println!("\nAll {} tests passed", checks.len());
+}
+
+fn main() {
+    let config = parse_args(env::args()).unwrap_or_else(|err| exit_with_usage_error(err));
+    match config.command {
+        Command::Run => run(&config),
+        Command::Sweep => sweep(&config),
+        Command::Theory => theory(&config),
+        Command::Scaling => scaling(&config),
+        Command::Bench => bench(&config),
+        Command::Replay => replay(&config),
+        Command::Verify => verify(&config),
+        Command::Help => println!("{}", USAGE),
+        Command::Version => println!("montecarlo {}", env!("CARGO_PKG_VERSION")),
+    }
 }
@@ -0,0 +1,715 @@
+use crate::distribution::Distribution;
+use crate::histogram::HistogramReport;
+use crate::result::{Z_95, Z_99};
+use crate::simulation::{Precision, SimulationConfig, SimulationRun};
+use crate::theory::{expected_measure, measure_variance, order_statistic_moments};
+use serde::{Serialize, Serializer};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Version of the JSON/CSV layout; bumped whenever a field is renamed, removed or moved, or
+/// may newly be absent. New CSV columns go at the end, so older readers keep their positions.
+pub const SCHEMA_VERSION: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "csv" => Some(OutputFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+const CSV_COLUMNS: &[&str] = &[
+    "schema_version",
+    "simulations",
+    "threads",
+    "points",
+    "seed",
+    "kernel",
+    "block_size",
+    "elapsed_seconds",
+    "samples_per_second",
+    "imbalance",
+    "time_limit_seconds",
+    "precision_kind",
+    "precision_target",
+    "precision_reached",
+    "k",
+    "label",
+    "estimate",
+    "variance",
+    "std_error",
+    "ci95_low",
+    "ci95_high",
+    "ci99_low",
+    "ci99_high",
+    "theoretical",
+    "difference",
+    "z_score",
+    "accumulation",
+    "rng",
+    "distribution",
+    "theoretical_variance",
+];
+
+/// Everything a run reports, in the shape serialized by `--format json`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub simulations: u64,
+    pub threads: u64,
+    pub points: usize,
+    pub seed: u64,
+    pub kernel: &'static str,
+    pub rng: &'static str,
+    pub distribution: String,
+    pub block_size: u64,
+    pub accumulation: &'static str,
+    pub elapsed_seconds: f64,
+    /// Simulations per second of wall time, over all workers
+    pub samples_per_second: f64,
+    /// Busiest worker's kernel time over the least busy one's; absent if a worker sat idle
+    pub imbalance: Option<f64>,
+    pub workers: Vec<WorkerReport>,
+    /// Present for time-limited runs
+    pub time_limit_seconds: Option<f64>,
+    /// Present for precision-targeted runs
+    pub precision: Option<PrecisionReport>,
+    pub statistics: Vec<StatisticReport>,
+    /// Minimum and maximum histograms of `--histogram` runs, only one of them for a single
+    /// point, otherwise empty
+    pub histograms: Vec<HistogramReport>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WorkerReport {
+    pub thread: usize,
+    pub blocks: u64,
+    pub samples: u64,
+    pub busy_seconds: f64,
+    pub samples_per_second: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PrecisionReport {
+    /// `"std_error"` or `"relative"`
+    pub kind: &'static str,
+    pub target: f64,
+    /// Whether every targeted statistic met the target before the simulation cap
+    pub reached: bool,
+    /// Labels of the statistics no number of trials gets to the target, which were left out
+    pub excluded: Vec<String>,
+}
+
+/// Estimate of one order statistic or measure next to its theoretical value.
+#[derive(Clone, Debug, Serialize)]
+pub struct StatisticReport {
+    /// Rank of an order statistic; absent for the measures of `--stats`
+    pub k: Option<usize>,
+    pub label: String,
+    pub estimate: f64,
+    /// Absent, as are the confidence intervals and `z_score`, below two simulations
+    pub variance: Option<f64>,
+    pub std_error: Option<f64>,
+    pub ci95: Option<[f64; 2]>,
+    pub ci99: Option<[f64; 2]>,
+    /// Absent where the expectation does not exist
+    pub theoretical: Option<f64>,
+    /// Signed `estimate - theoretical`
+    pub difference: Option<f64>,
+    pub z_score: Option<f64>,
+    /// Variance of the statistic itself, which `variance` estimates; absent where it is not
+    /// known, and `"inf"` in JSON and CSV for Cauchy order statistics next to the extremes
+    #[serde(serialize_with = "serialize_variance")]
+    pub theoretical_variance: Option<f64>,
+}
+
+/// Serializes a variance that may be infinite as `"inf"`, which is what the CSV output writes,
+/// rather than as the `null` serde_json writes for every non-finite number.
+pub fn serialize_variance<S: Serializer>(
+    variance: &Option<f64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match variance {
+        Some(variance) if variance.is_infinite() => serializer.serialize_str("inf"),
+        _ => variance.serialize(serializer),
+    }
+}
+
+pub fn order_statistic_label(k: usize, num_points: usize) -> String {
+    match k {
+        _ if num_points == 1 => "point".to_string(),
+        1 => "minimum".to_string(),
+        _ if k == num_points => "maximum".to_string(),
+        _ => format!("order statistic {}", k),
+    }
+}
+
+impl Report {
+    pub fn new(config: &SimulationConfig, run: &SimulationRun, elapsed: Duration) -> Report {
+        let result = &run.result;
+        let num_points = config.num_points;
+        let estimates = result.estimates();
+        let order_statistics = (1..=num_points).map(|k| {
+            let moments = order_statistic_moments(&config.distribution, k, num_points);
+            (
+                Some(k),
+                order_statistic_label(k, num_points),
+                moments.map(|moments| moments.mean),
+                moments.map(|moments| moments.variance),
+            )
+        });
+        let measures = result.measures.iter().map(|&measure| {
+            (
+                None,
+                measure.label(),
+                expected_measure(&config.distribution, measure, num_points),
+                measure_variance(&config.distribution, measure, num_points),
+            )
+        });
+        let statistics = order_statistics
+            .chain(measures)
+            .zip(estimates.iter())
+            .map(
+                |((k, label, theoretical, theoretical_variance), estimate)| {
+                    let interval = |z| {
+                        estimate
+                            .confidence_interval(z)
+                            .map(|(low, high)| [low, high])
+                    };
+                    StatisticReport {
+                        k,
+                        label,
+                        estimate: estimate.mean,
+                        variance: estimate.variance,
+                        std_error: estimate.std_error,
+                        ci95: interval(Z_95),
+                        ci99: interval(Z_99),
+                        theoretical,
+                        difference: theoretical.map(|theoretical| estimate.mean - theoretical),
+                        z_score: theoretical.and_then(|theoretical| estimate.z_score(theoretical)),
+                        theoretical_variance,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+        let precision = config.precision.map(|precision| {
+            let (kind, target) = match precision {
+                Precision::StdError(target) => ("std_error", target),
+                Precision::Relative(target) => ("relative", target),
+            };
+            let targeted = config.precision_targets();
+            PrecisionReport {
+                kind,
+                target,
+                reached: precision.is_met(&estimates, &targeted),
+                excluded: statistics
+                    .iter()
+                    .zip(&targeted)
+                    .filter(|&(_, &targeted)| !targeted)
+                    .map(|(stat, _)| stat.label.clone())
+                    .collect(),
+            }
+        });
+        let histograms = result
+            .histograms
+            .as_ref()
+            .map_or_else(Vec::new, |histograms| {
+                let mut reports = vec![HistogramReport::new(
+                    &histograms.minimum,
+                    &config.distribution,
+                    1,
+                    num_points,
+                )];
+                // A single point is its own minimum and maximum, and both bin the same trials
+                if num_points > 1 {
+                    reports.push(HistogramReport::new(
+                        &histograms.maximum,
+                        &config.distribution,
+                        num_points,
+                        num_points,
+                    ));
+                }
+                reports
+            });
+
+        Report {
+            schema_version: SCHEMA_VERSION,
+            simulations: result.count,
+            samples_per_second: result.count as f64 / elapsed.as_secs_f64(),
+            imbalance: run.imbalance(),
+            workers: run
+                .workers
+                .iter()
+                .enumerate()
+                .map(|(thread, worker)| WorkerReport {
+                    thread,
+                    blocks: worker.blocks,
+                    samples: worker.samples,
+                    busy_seconds: worker.busy_seconds,
+                    samples_per_second: worker.samples_per_second(),
+                })
+                .collect(),
+            threads: config.num_threads,
+            points: num_points,
+            seed: config.seed,
+            kernel: config.kernel.name(),
+            rng: config.rng_name(),
+            distribution: config.distribution.to_string(),
+            block_size: config.block_size,
+            accumulation: config.accumulation.name(),
+            elapsed_seconds: elapsed.as_secs_f64(),
+            time_limit_seconds: config.time_limit.map(|limit| limit.as_secs_f64()),
+            precision,
+            statistics,
+            histograms,
+        }
+    }
+
+    pub fn write(&self, format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            OutputFormat::Text => self.write_text(out),
+            OutputFormat::Json => self.write_json(out),
+            OutputFormat::Csv => self.write_csv(out),
+        }
+    }
+
+    pub fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "\nSimulation completed in {:.2} seconds",
+            self.elapsed_seconds
+        )?;
+        writeln!(out, "Number of simulations: {}", self.simulations)?;
+        writeln!(out, "Number of threads: {}", self.threads)?;
+        writeln!(out, "Number of points: {}", self.points)?;
+        writeln!(out, "Seed: {}", self.seed)?;
+        writeln!(out, "Kernel: {}", self.kernel)?;
+        writeln!(out, "Generator: {}", self.rng)?;
+        writeln!(out, "Distribution: {}", self.distribution)?;
+        writeln!(out, "Block size: {}", self.block_size)?;
+        writeln!(out, "Accumulation: {}", self.accumulation)?;
+        writeln!(
+            out,
+            "Throughput: {:.1} M samples/s",
+            self.samples_per_second / 1e6
+        )?;
+        for worker in &self.workers {
+            writeln!(
+                out,
+                "  Worker {}: {} blocks, {} samples in {:.3} s ({:.1} M samples/s)",
+                worker.thread,
+                worker.blocks,
+                worker.samples,
+                worker.busy_seconds,
+                worker.samples_per_second / 1e6
+            )?;
+        }
+        match self.imbalance {
+            Some(imbalance) => writeln!(
+                out,
+                "Load imbalance (slowest/fastest worker): {:.3}",
+                imbalance
+            )?,
+            None => writeln!(
+                out,
+                "Load imbalance (slowest/fastest worker): n/a, some workers got no blocks"
+            )?,
+        }
+        if let Some(limit) = self.time_limit_seconds {
+            writeln!(out, "Time limit: {:.2} seconds", limit)?;
+        }
+        if let Some(precision) = &self.precision {
+            let target = match precision.kind {
+                "relative" => format!("relative standard error {:e}", precision.target),
+                _ => format!("standard error {:e}", precision.target),
+            };
+            if precision.reached {
+                writeln!(
+                    out,
+                    "Target {} reached after {} simulations",
+                    target, self.simulations
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "Target {} not reached within {} simulations",
+                    target, self.simulations
+                )?;
+            }
+            if !precision.excluded.is_empty() {
+                writeln!(
+                    out,
+                    "Left out of the target, which no number of simulations reaches: {}",
+                    precision.excluded.join(", ")
+                )?;
+            }
+        }
+        for stat in &self.statistics {
+            writeln!(
+                out,
+                "\nExpected value of {}: {:.8}",
+                stat.label, stat.estimate
+            )?;
+            let (Some(variance), Some(std_error), Some(ci95), Some(ci99)) =
+                (stat.variance, stat.std_error, stat.ci95, stat.ci99)
+            else {
+                writeln!(out, "  Sample variance: undefined, too few simulations")?;
+                continue;
+            };
+            writeln!(out, "  Sample variance: {:.8}", variance)?;
+            writeln!(out, "  Standard error: {:.8}", std_error)?;
+            writeln!(
+                out,
+                "  95% confidence interval: [{:.8}, {:.8}]",
+                ci95[0], ci95[1]
+            )?;
+            writeln!(
+                out,
+                "  99% confidence interval: [{:.8}, {:.8}]",
+                ci99[0], ci99[1]
+            )?;
+        }
+
+        writeln!(out)?;
+        for stat in &self.statistics {
+            match (stat.theoretical, stat.theoretical_variance) {
+                (Some(theoretical), Some(variance)) => writeln!(
+                    out,
+                    "Theoretical expected value of {}: {:.8} (variance {:.8})",
+                    stat.label, theoretical, variance
+                )?,
+                (Some(theoretical), None) => writeln!(
+                    out,
+                    "Theoretical expected value of {}: {:.8}",
+                    stat.label, theoretical
+                )?,
+                (None, _) => writeln!(
+                    out,
+                    "Theoretical expected value of {}: undefined",
+                    stat.label
+                )?,
+            }
+        }
+        for stat in &self.statistics {
+            match (stat.difference, stat.z_score) {
+                (Some(difference), Some(z_score)) => writeln!(
+                    out,
+                    "Difference from theoretical ({}): {:.8} (z = {:.3})",
+                    stat.label,
+                    difference.abs(),
+                    z_score
+                )?,
+                (Some(difference), None) => writeln!(
+                    out,
+                    "Difference from theoretical ({}): {:.8} (z undefined)",
+                    stat.label,
+                    difference.abs()
+                )?,
+                (None, _) => writeln!(
+                    out,
+                    "Difference from theoretical ({}): undefined",
+                    stat.label
+                )?,
+            }
+        }
+        for histogram in &self.histograms {
+            histogram.write_plot(out)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, self)?;
+        writeln!(out)
+    }
+
+    /// One row per order statistic and measure, with the run-level fields repeated on every row.
+    /// Per-worker statistics are only available in the text and JSON formats.
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        Report::write_csv_header(out)?;
+        self.write_csv_rows(out)
+    }
+
+    pub fn write_csv_header(out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{}", CSV_COLUMNS.join(","))
+    }
+
+    /// The rows of [`Report::write_csv`] without the header, so several runs can share one table.
+    pub fn write_csv_rows(&self, out: &mut impl Write) -> io::Result<()> {
+        // Optional fields are left empty when they do not apply to the run
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let run_fields = [
+            self.schema_version.to_string(),
+            self.simulations.to_string(),
+            self.threads.to_string(),
+            self.points.to_string(),
+            self.seed.to_string(),
+            self.kernel.to_string(),
+            self.block_size.to_string(),
+            self.elapsed_seconds.to_string(),
+            self.samples_per_second.to_string(),
+            optional(self.imbalance.map(|imbalance| imbalance.to_string())),
+            optional(self.time_limit_seconds.map(|limit| limit.to_string())),
+            optional(self.precision.as_ref().map(|p| p.kind.to_string())),
+            optional(self.precision.as_ref().map(|p| p.target.to_string())),
+            optional(self.precision.as_ref().map(|p| p.reached.to_string())),
+        ];
+        for stat in &self.statistics {
+            let stat_fields = [
+                optional(stat.k.map(|k| k.to_string())),
+                stat.label.clone(),
+                stat.estimate.to_string(),
+                optional(stat.variance.map(|variance| variance.to_string())),
+                optional(stat.std_error.map(|std_error| std_error.to_string())),
+                optional(stat.ci95.map(|ci95| ci95[0].to_string())),
+                optional(stat.ci95.map(|ci95| ci95[1].to_string())),
+                optional(stat.ci99.map(|ci99| ci99[0].to_string())),
+                optional(stat.ci99.map(|ci99| ci99[1].to_string())),
+                optional(stat.theoretical.map(|theoretical| theoretical.to_string())),
+                optional(stat.difference.map(|difference| difference.to_string())),
+                optional(stat.z_score.map(|z_score| z_score.to_string())),
+            ];
+            // Columns added since, in the order they were added
+            let later_fields = [
+                self.accumulation.to_string(),
+                self.rng.to_string(),
+                // Quoted, since the parameter list contains commas
+                format!("\"{}\"", self.distribution),
+                optional(
+                    stat.theoretical_variance
+                        .map(|variance| variance.to_string()),
+                ),
+            ];
+            writeln!(
+                out,
+                "{},{},{}",
+                run_fields.join(","),
+                stat_fields.join(","),
+                later_fields.join(",")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the reports of a `sweep`, one per point count, as a single table.
+pub fn write_sweep(
+    reports: &[Report],
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            writeln!(
+                out,
+                "| Points | Statistic | Estimate | Std. error | Theoretical | z | Time [s] |"
+            )?;
+            writeln!(out, "|---:|:---|---:|---:|---:|---:|---:|")?;
+            let undefined = || "undefined".to_string();
+            for report in reports {
+                for stat in &report.statistics {
+                    writeln!(
+                        out,
+                        "| {} | {} | {:.8} | {} | {} | {} | {:.2} |",
+                        report.points,
+                        stat.label,
+                        stat.estimate,
+                        stat.std_error
+                            .map_or_else(undefined, |std_error| format!("{:.8}", std_error)),
+                        stat.theoretical
+                            .map_or_else(undefined, |theoretical| format!("{:.8}", theoretical)),
+                        stat.z_score
+                            .map_or_else(undefined, |z_score| format!("{:.3}", z_score)),
+                        report.elapsed_seconds
+                    )?;
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, reports)?;
+            writeln!(out)
+        }
+        OutputFormat::Csv => {
+            Report::write_csv_header(out)?;
+            for report in reports {
+                report.write_csv_rows(out)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// One row of the `theory` command's output; the moments are absent where they do not exist.
+#[derive(Clone, Debug, Serialize)]
+pub struct TheoryRow {
+    pub points: usize,
+    pub distribution: String,
+    pub k: usize,
+    pub label: String,
+    pub mean: Option<f64>,
+    #[serde(serialize_with = "serialize_variance")]
+    pub variance: Option<f64>,
+    #[serde(serialize_with = "serialize_variance")]
+    pub std_dev: Option<f64>,
+}
+
+/// The exact moments of every order statistic of `num_points` points.
+pub fn theory_rows(distribution: &Distribution, num_points: usize) -> Vec<TheoryRow> {
+    (1..=num_points)
+        .map(|k| {
+            let moments = order_statistic_moments(distribution, k, num_points);
+            TheoryRow {
+                points: num_points,
+                distribution: distribution.to_string(),
+                k,
+                label: order_statistic_label(k, num_points),
+                mean: moments.map(|moments| moments.mean),
+                variance: moments.map(|moments| moments.variance),
+                std_dev: moments.map(|moments| moments.variance.sqrt()),
+            }
+        })
+        .collect()
+}
+
+pub fn write_theory(
+    rows: &[TheoryRow],
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            if let Some(row) = rows.first() {
+                writeln!(
+                    out,
+                    "Order statistics of {} point(s) from {}:\n",
+                    row.points, row.distribution
+                )?;
+            }
+            writeln!(out, "| k | Statistic | Mean | Variance | Std. deviation |")?;
+            writeln!(out, "|---:|:---|---:|---:|---:|")?;
+            let cell = |value: Option<f64>| {
+                value.map_or_else(|| "undefined".to_string(), |value| format!("{:.8}", value))
+            };
+            for row in rows {
+                writeln!(
+                    out,
+                    "| {} | {} | {} | {} | {} |",
+                    row.k,
+                    row.label,
+                    cell(row.mean),
+                    cell(row.variance),
+                    cell(row.std_dev)
+                )?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, rows)?;
+            writeln!(out)
+        }
+        OutputFormat::Csv => {
+            writeln!(out, "points,distribution,k,label,mean,variance,std_dev")?;
+            let cell =
+                |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+            for row in rows {
+                writeln!(
+                    out,
+                    "{},\"{}\",{},{},{},{},{}",
+                    row.points,
+                    row.distribution,
+                    row.k,
+                    row.label,
+                    cell(row.mean),
+                    cell(row.variance),
+                    cell(row.std_dev)
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// One trial of a counter-based run, as printed by the `replay` command.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReplayedTrial {
+    pub seed: u64,
+    pub trial: u64,
+    /// In the order they were drawn
+    pub points: Vec<f64>,
+    pub sorted: Vec<f64>,
+    pub minimum: f64,
+    pub maximum: f64,
+}
+
+impl ReplayedTrial {
+    /// Takes the points returned by [`crate::replay_trial`], which are never empty.
+    pub fn new(seed: u64, trial: u64, points: Vec<f64>) -> ReplayedTrial {
+        let mut sorted = points.clone();
+        sorted.sort_by(f64::total_cmp);
+        ReplayedTrial {
+            seed,
+            trial,
+            minimum: sorted[0],
+            maximum: sorted[sorted.len() - 1],
+            points,
+            sorted,
+        }
+    }
+
+    pub fn write(&self, format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+        let list = |values: &[f64]| {
+            values
+                .iter()
+                .map(|value| format!("{:.8}", value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match format {
+            OutputFormat::Text => {
+                writeln!(
+                    out,
+                    "Trial {} of seed {} ({} point(s), philox):",
+                    self.trial,
+                    self.seed,
+                    self.points.len()
+                )?;
+                writeln!(out, "  Points in draw order: {}", list(&self.points))?;
+                writeln!(out, "  Sorted: {}", list(&self.sorted))?;
+                writeln!(out, "  Minimum: {:.8}", self.minimum)?;
+                writeln!(out, "  Maximum: {:.8}", self.maximum)
+            }
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut *out, self)?;
+                writeln!(out)
+            }
+            OutputFormat::Csv => {
+                writeln!(out, "seed,trial,draw,point,k,sorted")?;
+                for (draw, (point, sorted)) in self.points.iter().zip(&self.sorted).enumerate() {
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{}",
+                        self.seed,
+                        self.trial,
+                        draw,
+                        point,
+                        draw + 1,
+                        sorted
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
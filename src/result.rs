@@ -0,0 +1,231 @@
+use crate::histogram::ExtremeHistograms;
+use crate::statistic::Measure;
+
+// Two-sided standard normal quantiles for the reported confidence intervals
+pub const Z_95: f64 = 1.959_963_984_540_054;
+pub const Z_99: f64 = 2.575_829_303_548_901;
+
+/// How the kernels add up samples inside a block.
+///
+/// Merging blocks is always compensated; it costs nothing next to the kernels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Accumulation {
+    /// Plain `+=`; the rounding error grows with the number of terms per lane
+    #[default]
+    Naive,
+    /// Every addition also accumulates its exact rounding error, see [`CompensatedSum`]
+    Compensated,
+}
+
+impl Accumulation {
+    pub fn from_name(name: &str) -> Option<Accumulation> {
+        match name {
+            "naive" => Some(Accumulation::Naive),
+            "compensated" => Some(Accumulation::Compensated),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Accumulation::Naive => "naive",
+            Accumulation::Compensated => "compensated",
+        }
+    }
+}
+
+/// Knuth's TwoSum: `a + b` rounded, and the exact error of that rounding.
+#[inline]
+pub fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let error = (a - (sum - b_virtual)) + (b - b_virtual);
+    (sum, error)
+}
+
+/// Running sum that keeps the rounding error of every addition in a second term.
+///
+/// This is Neumaier's variant of Kahan summation written with the branch-free TwoSum, so
+/// `value()` stays within a few ulps of the exact sum however many terms are added.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CompensatedSum {
+    pub sum: f64,
+    pub compensation: f64,
+}
+
+impl CompensatedSum {
+    #[inline]
+    pub fn add(&mut self, value: f64) {
+        let (sum, error) = two_sum(self.sum, value);
+        self.sum = sum;
+        self.compensation += error;
+    }
+
+    pub fn merge(&mut self, other: &CompensatedSum) {
+        self.add(other.sum);
+        self.compensation += other.compensation;
+    }
+
+    pub fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+/// Running sums for every order statistic over a batch of trials.
+///
+/// Every point has `shift` subtracted before it is summed, so the sums of squares keep their
+/// precision for laws far from 0. Partial results from different blocks or threads combine
+/// with [`SimulationResult::merge`], which needs the same shift on both.
+#[derive(Clone, Debug)]
+pub struct SimulationResult {
+    pub count: u64,
+    /// Subtracted from every point, and from the measures as [`Measure::weight_sum`] says
+    pub shift: f64,
+    /// `order_sums[k]` accumulates the (k + 1)-th smallest point of every trial
+    pub order_sums: Vec<CompensatedSum>,
+    pub order_sq_sums: Vec<CompensatedSum>,
+    /// Values accumulated next to the order statistics, see [`Statistic`](crate::Statistic)
+    pub measures: Vec<Measure>,
+    /// `measure_sums[i]` accumulates `measures[i]` of every trial
+    pub measure_sums: Vec<CompensatedSum>,
+    pub measure_sq_sums: Vec<CompensatedSum>,
+    /// Minimum and maximum of every trial, if the run bins them
+    pub histograms: Option<ExtremeHistograms>,
+}
+
+#[inline]
+fn accumulate(
+    sum: &mut CompensatedSum,
+    sq_sum: &mut CompensatedSum,
+    value: f64,
+    accumulation: Accumulation,
+) {
+    match accumulation {
+        Accumulation::Naive => {
+            sum.sum += value;
+            sq_sum.sum += value * value;
+        }
+        Accumulation::Compensated => {
+            sum.add(value);
+            sq_sum.add(value * value);
+        }
+    }
+}
+
+impl SimulationResult {
+    pub fn new(num_points: usize) -> Self {
+        SimulationResult::with_measures(num_points, Vec::new())
+    }
+
+    pub fn with_measures(num_points: usize, measures: Vec<Measure>) -> Self {
+        SimulationResult {
+            count: 0,
+            shift: 0.0,
+            order_sums: vec![CompensatedSum::default(); num_points],
+            order_sq_sums: vec![CompensatedSum::default(); num_points],
+            measure_sums: vec![CompensatedSum::default(); measures.len()],
+            measure_sq_sums: vec![CompensatedSum::default(); measures.len()],
+            measures,
+            histograms: None,
+        }
+    }
+
+    /// Adds one trial, given its points in ascending order.
+    pub fn add_trial(&mut self, points: &[f64], accumulation: Accumulation) {
+        self.count += 1;
+        for ((sum, sq_sum), point) in self
+            .order_sums
+            .iter_mut()
+            .zip(self.order_sq_sums.iter_mut())
+            .zip(points)
+        {
+            accumulate(sum, sq_sum, *point - self.shift, accumulation);
+        }
+        for ((sum, sq_sum), measure) in self
+            .measure_sums
+            .iter_mut()
+            .zip(self.measure_sq_sums.iter_mut())
+            .zip(&self.measures)
+        {
+            let shift = measure.weight_sum() * self.shift;
+            accumulate(sum, sq_sum, measure.evaluate(points) - shift, accumulation);
+        }
+        if let Some(histograms) = &mut self.histograms {
+            histograms.add(points[0], points[points.len() - 1]);
+        }
+    }
+
+    pub fn merge(&mut self, other: &SimulationResult) {
+        debug_assert_eq!(self.measures, other.measures);
+        debug_assert_eq!(self.shift, other.shift);
+        self.count += other.count;
+        let totals = self
+            .order_sums
+            .iter_mut()
+            .chain(self.order_sq_sums.iter_mut())
+            .chain(self.measure_sums.iter_mut())
+            .chain(self.measure_sq_sums.iter_mut());
+        let sums = other
+            .order_sums
+            .iter()
+            .chain(&other.order_sq_sums)
+            .chain(&other.measure_sums)
+            .chain(&other.measure_sq_sums);
+        for (total, sum) in totals.zip(sums) {
+            total.merge(sum);
+        }
+        if let (Some(histograms), Some(other)) = (&mut self.histograms, &other.histograms) {
+            histograms.merge(other);
+        }
+    }
+
+    /// Estimates of every order statistic, in ascending order, followed by those of the measures.
+    pub fn estimates(&self) -> Vec<Estimate> {
+        let n = self.count as f64;
+        let shifts = self.order_sums.iter().map(|_| self.shift).chain(
+            self.measures
+                .iter()
+                .map(|measure| measure.weight_sum() * self.shift),
+        );
+        self.order_sums
+            .iter()
+            .zip(&self.order_sq_sums)
+            .chain(self.measure_sums.iter().zip(&self.measure_sq_sums))
+            .zip(shifts)
+            .map(|((sum, sq_sum), shift)| {
+                let (sum, sq_sum) = (sum.value(), sq_sum.value());
+                // Mean of the shifted values, which is small next to their spread
+                let offset = sum / n;
+                // Unbiased sample variance; clamped because rounding can push it below zero
+                let variance =
+                    (self.count > 1).then(|| ((sq_sum - sum * offset) / (n - 1.0)).max(0.0));
+                Estimate {
+                    mean: shift + offset,
+                    variance,
+                    std_error: variance.map(|variance| (variance / n).sqrt()),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Point estimate of one statistic's expectation with its sampling error.
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+    pub mean: f64,
+    /// Absent below two trials, which leave the spread unknown
+    pub variance: Option<f64>,
+    pub std_error: Option<f64>,
+}
+
+impl Estimate {
+    pub fn confidence_interval(&self, z: f64) -> Option<(f64, f64)> {
+        self.std_error
+            .map(|std_error| (self.mean - z * std_error, self.mean + z * std_error))
+    }
+
+    pub fn z_score(&self, theoretical: f64) -> Option<f64> {
+        self.std_error
+            .map(|std_error| (self.mean - theoretical) / std_error)
+    }
+}
@@ -0,0 +1,343 @@
+use rand::{RngCore, SeedableRng};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Generators the scalar kernel can draw its points from.
+///
+/// The SIMD kernels always use their own in-register xoshiro256+ streams.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RngKind {
+    Pcg64,
+    #[default]
+    Pcg64Mcg,
+    Xoshiro256PlusPlus,
+    ChaCha8,
+    ChaCha20,
+    /// `rand`'s `StdRng`, currently ChaCha12; its algorithm may change between `rand` releases
+    StdRng,
+    /// Counter-based [`Philox4x32`]; every trial's points are a pure function of the seed and
+    /// the trial's index, see [`crate::replay_trial`]
+    Philox,
+}
+
+impl RngKind {
+    pub const ALL: [RngKind; 7] = [
+        RngKind::Pcg64,
+        RngKind::Pcg64Mcg,
+        RngKind::Xoshiro256PlusPlus,
+        RngKind::ChaCha8,
+        RngKind::ChaCha20,
+        RngKind::StdRng,
+        RngKind::Philox,
+    ];
+
+    pub fn from_name(name: &str) -> Option<RngKind> {
+        match name {
+            "pcg64" => Some(RngKind::Pcg64),
+            "pcg64mcg" => Some(RngKind::Pcg64Mcg),
+            "xoshiro256++" | "xoshiro256pp" => Some(RngKind::Xoshiro256PlusPlus),
+            "chacha8" => Some(RngKind::ChaCha8),
+            "chacha20" => Some(RngKind::ChaCha20),
+            "std" => Some(RngKind::StdRng),
+            "philox" => Some(RngKind::Philox),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RngKind::Pcg64 => "pcg64",
+            RngKind::Pcg64Mcg => "pcg64mcg",
+            RngKind::Xoshiro256PlusPlus => "xoshiro256++",
+            RngKind::ChaCha8 => "chacha8",
+            RngKind::ChaCha20 => "chacha20",
+            RngKind::StdRng => "std",
+            RngKind::Philox => "philox",
+        }
+    }
+}
+
+// Hashing constants of NumPy's SeedSequence, after Melissa O'Neill's seed_seq_fe
+const INIT_A: u32 = 0x43b0_d7e5;
+const MULT_A: u32 = 0x931e_8875;
+const INIT_B: u32 = 0x8b51_f9dd;
+const MULT_B: u32 = 0x58f3_8ded;
+const MIX_MULT_L: u32 = 0xca01_f9dd;
+const MIX_MULT_R: u32 = 0x4973_f715;
+const XSHIFT: u32 = 16;
+
+/// Seed derivation in the style of NumPy's `SeedSequence`.
+///
+/// Up to 128 bits of entropy (the master seed) and a spawn key (the block index) are hashed
+/// together into a 128-bit pool. The pool then expands into a generator state of any width,
+/// so 128- and 256-bit generators get full states instead of a widened 64-bit seed. Every
+/// input word influences every output word, so nearby seeds and nearby block indices give
+/// unrelated streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeedSequence {
+    pool: [u32; 4],
+}
+
+fn hashmix(value: u32, hash_const: &mut u32) -> u32 {
+    let mut value = value ^ *hash_const;
+    *hash_const = hash_const.wrapping_mul(MULT_A);
+    value = value.wrapping_mul(*hash_const);
+    value ^ (value >> XSHIFT)
+}
+
+fn mix(x: u32, y: u32) -> u32 {
+    let result = MIX_MULT_L
+        .wrapping_mul(x)
+        .wrapping_sub(MIX_MULT_R.wrapping_mul(y));
+    result ^ (result >> XSHIFT)
+}
+
+impl SeedSequence {
+    pub fn new(entropy: u128, spawn_key: &[u64]) -> SeedSequence {
+        // The entropy fills the pool exactly, so the spawn key words are always mixed in on top
+        // and no two (entropy, spawn_key) pairs flatten to the same input
+        let words: Vec<u32> = (0..4)
+            .map(|i| (entropy >> (32 * i)) as u32)
+            .chain(
+                spawn_key
+                    .iter()
+                    .flat_map(|&word| [word as u32, (word >> 32) as u32]),
+            )
+            .collect();
+
+        let mut hash_const = INIT_A;
+        let mut pool = [0u32; 4];
+        for (i, slot) in pool.iter_mut().enumerate() {
+            *slot = hashmix(words.get(i).copied().unwrap_or(0), &mut hash_const);
+        }
+        for src in 0..pool.len() {
+            for dst in 0..pool.len() {
+                if src != dst {
+                    pool[dst] = mix(pool[dst], hashmix(pool[src], &mut hash_const));
+                }
+            }
+        }
+        for &word in words.iter().skip(pool.len()) {
+            for slot in pool.iter_mut() {
+                *slot = mix(*slot, hashmix(word, &mut hash_const));
+            }
+        }
+        SeedSequence { pool }
+    }
+
+    /// Fills `state` with words derived from the pool.
+    pub fn generate_state(&self, state: &mut [u32]) {
+        let mut hash_const = INIT_B;
+        for (word, &source) in state.iter_mut().zip(self.pool.iter().cycle()) {
+            let mut value = source ^ hash_const;
+            hash_const = hash_const.wrapping_mul(MULT_B);
+            value = value.wrapping_mul(hash_const);
+            *word = value ^ (value >> XSHIFT);
+        }
+    }
+
+    pub fn generate_u64(&self, state: &mut [u64]) {
+        let mut words = vec![0u32; 2 * state.len()];
+        self.generate_state(&mut words);
+        for (word, halves) in state.iter_mut().zip(words.chunks_exact(2)) {
+            *word = u64::from(halves[0]) | u64::from(halves[1]) << 32;
+        }
+    }
+
+    /// A generator whose whole seed, e.g. all 128 bits of `Pcg64Mcg`'s state, comes from the pool.
+    pub fn seed_rng<R: SeedableRng>(&self) -> R {
+        let mut seed = R::Seed::default();
+        let bytes = seed.as_mut();
+        let mut words = vec![0u32; bytes.len().div_ceil(4)];
+        self.generate_state(&mut words);
+        for (chunk, word) in bytes.chunks_mut(4).zip(&words) {
+            chunk.copy_from_slice(&word.to_le_bytes()[..chunk.len()]);
+        }
+        R::from_seed(seed)
+    }
+}
+
+const PHILOX_M0: u32 = 0xd251_1f53;
+const PHILOX_M1: u32 = 0xcd9e_8d57;
+const PHILOX_W0: u32 = 0x9e37_79b9;
+const PHILOX_W1: u32 = 0xbb67_ae85;
+
+/// Philox4x32-10, the counter-based generator of Salmon et al., "Parallel random numbers: as
+/// easy as 1, 2, 3" (SC '11).
+///
+/// Every output block is a keyed bijection of a 128-bit counter, so any position of the stream
+/// can be reached in constant time. The upper 64 bits of the counter hold the trial index and
+/// the lower ones count the draws within the trial, see [`Philox4x32::seek_trial`].
+#[derive(Clone, Debug)]
+pub struct Philox4x32 {
+    key: [u32; 2],
+    counter: u128,
+    buffer: [u32; 4],
+    /// Next unused word of `buffer`; 4 means the buffer is spent
+    index: usize,
+}
+
+impl Philox4x32 {
+    pub fn new(key: [u32; 2]) -> Philox4x32 {
+        Philox4x32 {
+            key,
+            counter: 0,
+            buffer: [0; 4],
+            index: 4,
+        }
+    }
+
+    /// The ten rounds applied to `counter`.
+    pub fn block(key: [u32; 2], counter: [u32; 4]) -> [u32; 4] {
+        let mut key = key;
+        let mut x = counter;
+        for round in 0..10 {
+            if round > 0 {
+                key = [
+                    key[0].wrapping_add(PHILOX_W0),
+                    key[1].wrapping_add(PHILOX_W1),
+                ];
+            }
+            let product0 = u64::from(PHILOX_M0) * u64::from(x[0]);
+            let product1 = u64::from(PHILOX_M1) * u64::from(x[2]);
+            x = [
+                (product1 >> 32) as u32 ^ x[1] ^ key[0],
+                product1 as u32,
+                (product0 >> 32) as u32 ^ x[3] ^ key[1],
+                product0 as u32,
+            ];
+        }
+        x
+    }
+
+    /// Moves to the first draw of trial `trial`.
+    pub fn seek_trial(&mut self, trial: u64) {
+        self.counter = u128::from(trial) << 64;
+        self.index = 4;
+    }
+
+    fn refill(&mut self) {
+        let counter = [0, 32, 64, 96].map(|shift| (self.counter >> shift) as u32);
+        self.buffer = Philox4x32::block(self.key, counter);
+        self.counter = self.counter.wrapping_add(1);
+        self.index = 0;
+    }
+}
+
+impl RngCore for Philox4x32 {
+    fn next_u32(&mut self) -> u32 {
+        if self.index == 4 {
+            self.refill();
+        }
+        self.index += 1;
+        self.buffer[self.index - 1]
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        u64::from(self.next_u32()) | u64::from(self.next_u32()) << 32
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Philox4x32 {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: [u8; 8]) -> Philox4x32 {
+        let word = |i: usize| u32::from_le_bytes([seed[i], seed[i + 1], seed[i + 2], seed[i + 3]]);
+        Philox4x32::new([word(0), word(4)])
+    }
+}
+
+/// Four interleaved xoshiro256+ streams, one per 64-bit lane of an AVX2 register.
+///
+/// Uniforms are built in-register from the top 52 bits of each output, so the
+/// kernels never round-trip through scalar `rng.gen()` calls.
+#[cfg(target_arch = "x86_64")]
+pub(crate) struct Xoshiro256PlusX4 {
+    s: [__m256i; 4],
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Xoshiro256PlusX4 {
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn new(seeds: &SeedSequence) -> Self {
+        let mut words = [[0u64; 4]; 4];
+        seeds.generate_u64(words.as_flattened_mut());
+        Xoshiro256PlusX4 {
+            s: words.map(|word| _mm256_loadu_si256(word.as_ptr() as *const __m256i)),
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn next_f64(&mut self) -> __m256d {
+        let [s0, s1, s2, s3] = self.s;
+        let result = _mm256_add_epi64(s0, s3);
+        let t = _mm256_slli_epi64::<17>(s1);
+
+        let s2 = _mm256_xor_si256(s2, s0);
+        let s3 = _mm256_xor_si256(s3, s1);
+        let s1 = _mm256_xor_si256(s1, s2);
+        let s0 = _mm256_xor_si256(s0, s3);
+        let s2 = _mm256_xor_si256(s2, t);
+        let s3 = _mm256_or_si256(_mm256_slli_epi64::<45>(s3), _mm256_srli_epi64::<19>(s3));
+        self.s = [s0, s1, s2, s3];
+
+        // Exponent of 1.0 over a random mantissa gives [1, 2); shift down to [0, 1)
+        let bits = _mm256_or_si256(
+            _mm256_srli_epi64::<12>(result),
+            _mm256_set1_epi64x(0x3ff0_0000_0000_0000),
+        );
+        _mm256_sub_pd(_mm256_castsi256_pd(bits), _mm256_set1_pd(1.0))
+    }
+}
+
+/// Eight-lane AVX-512 counterpart of [`Xoshiro256PlusX4`].
+#[cfg(target_arch = "x86_64")]
+pub(crate) struct Xoshiro256PlusX8 {
+    s: [__m512i; 4],
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Xoshiro256PlusX8 {
+    #[target_feature(enable = "avx512f")]
+    pub(crate) unsafe fn new(seeds: &SeedSequence) -> Self {
+        let mut words = [[0u64; 8]; 4];
+        seeds.generate_u64(words.as_flattened_mut());
+        Xoshiro256PlusX8 {
+            s: words.map(|word| _mm512_loadu_epi64(word.as_ptr() as *const i64)),
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    pub(crate) unsafe fn next_f64(&mut self) -> __m512d {
+        let [s0, s1, s2, s3] = self.s;
+        let result = _mm512_add_epi64(s0, s3);
+        let t = _mm512_slli_epi64::<17>(s1);
+
+        let s2 = _mm512_xor_si512(s2, s0);
+        let s3 = _mm512_xor_si512(s3, s1);
+        let s1 = _mm512_xor_si512(s1, s2);
+        let s0 = _mm512_xor_si512(s0, s3);
+        let s2 = _mm512_xor_si512(s2, t);
+        let s3 = _mm512_rol_epi64::<45>(s3);
+        self.s = [s0, s1, s2, s3];
+
+        let bits = _mm512_or_si512(
+            _mm512_srli_epi64::<12>(result),
+            _mm512_set1_epi64(0x3ff0_0000_0000_0000),
+        );
+        _mm512_sub_pd(_mm512_castsi512_pd(bits), _mm512_set1_pd(1.0))
+    }
+}
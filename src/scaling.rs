@@ -0,0 +1,95 @@
+use crate::bench::{benchmark, BenchResult};
+use crate::error::Error;
+use crate::simulation::SimulationConfig;
+use std::io::{self, Write};
+
+/// Timing of one thread count in a strong-scaling study.
+#[derive(Clone, Debug)]
+pub struct ScalingRow {
+    pub result: BenchResult,
+    /// Mean time of the first row divided by this row's mean time
+    pub speedup: f64,
+    pub speedup_error: f64,
+    /// Speedup per thread added relative to the first row, 1.0 being perfect scaling
+    pub efficiency: f64,
+}
+
+/// Powers of two up to `max_threads`, always ending with `max_threads` itself.
+pub fn default_thread_counts(max_threads: u64) -> Vec<u64> {
+    let mut counts: Vec<u64> = (0..64)
+        .map(|shift| 1 << shift)
+        .take_while(|&threads| threads < max_threads)
+        .collect();
+    counts.push(max_threads.max(1));
+    counts
+}
+
+/// Speedup and efficiency of every result over the first one.
+pub fn scaling_rows(results: &[BenchResult]) -> Vec<ScalingRow> {
+    let Some(base) = results.first() else {
+        return Vec::new();
+    };
+    results
+        .iter()
+        .map(|result| {
+            let speedup = base.mean_seconds / result.mean_seconds;
+            // Relative errors of the two means add in quadrature
+            let speedup_error = speedup
+                * ((result.stddev_seconds / result.mean_seconds).powi(2)
+                    + (base.stddev_seconds / base.mean_seconds).powi(2))
+                .sqrt();
+            ScalingRow {
+                result: result.clone(),
+                speedup,
+                speedup_error,
+                efficiency: speedup * base.threads as f64 / result.threads as f64,
+            }
+        })
+        .collect()
+}
+
+/// Benchmarks the same fixed-size simulation at every thread count, see [`benchmark`].
+pub fn scaling_study(
+    config: &SimulationConfig,
+    thread_counts: &[u64],
+    warmup: u32,
+    runs: u32,
+) -> Result<Vec<ScalingRow>, Error> {
+    let results = benchmark(config, thread_counts, warmup, runs)?;
+    Ok(scaling_rows(&results))
+}
+
+/// Writes `rows` as a markdown table in the format of the README benchmarks, with `program`
+/// as the command that was run.
+pub fn write_scaling_table(
+    rows: &[ScalingRow],
+    program: &str,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "| Command | Mean [ms] | Min [ms] | Max [ms] | Speedup | Efficiency |"
+    )?;
+    writeln!(out, "|:---|---:|---:|---:|---:|---:|")?;
+    for (i, row) in rows.iter().enumerate() {
+        let result = &row.result;
+        let speedup = if i == 0 {
+            "1.00".to_string()
+        } else {
+            format!("{:.2} ± {:.2}", row.speedup, row.speedup_error)
+        };
+        writeln!(
+            out,
+            "| `{} -t {}` | {:.1} ± {:.1} | {:.1} | {:.1} | {} | {:.1}% |",
+            program,
+            result.threads,
+            result.mean_seconds * 1000.0,
+            result.stddev_seconds * 1000.0,
+            result.min_seconds * 1000.0,
+            result.max_seconds * 1000.0,
+            speedup,
+            row.efficiency * 100.0
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,407 @@
+//! The AVX2 and AVX-512 kernels, one generic body over the register type.
+//!
+//! Every [`Lanes`] method carries its instruction set's `target_feature`, so it only inlines
+//! into code compiled with that feature. The body and its helpers are therefore
+//! `#[inline(always)]`, and the entry points at the bottom enable the feature for all of it.
+
+use crate::distribution::Distribution;
+use crate::result::{Accumulation, CompensatedSum, SimulationResult};
+use crate::rng::{SeedSequence, Xoshiro256PlusX4, Xoshiro256PlusX8};
+use crate::statistic::{distance_weight, Measure};
+use std::arch::x86_64::*;
+
+/// SIMD register of `f64` lanes, each holding its own trial.
+trait Lanes: Copy {
+    /// The register as an array, one element per lane
+    type Array: Copy + Default + AsRef<[f64]> + AsMut<[f64]>;
+    /// Generator with one stream per lane
+    type Rng;
+
+    unsafe fn new_rng(seeds: &SeedSequence) -> Self::Rng;
+    /// Uniforms on [0, 1), one per lane.
+    unsafe fn uniform(rng: &mut Self::Rng) -> Self;
+    unsafe fn splat(value: f64) -> Self;
+    unsafe fn load(lanes: &Self::Array) -> Self;
+    unsafe fn store(self) -> Self::Array;
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn sub(self, other: Self) -> Self;
+    unsafe fn mul(self, other: Self) -> Self;
+    unsafe fn min(self, other: Self) -> Self;
+    unsafe fn max(self, other: Self) -> Self;
+}
+
+impl Lanes for __m256d {
+    type Array = [f64; 4];
+    type Rng = Xoshiro256PlusX4;
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn new_rng(seeds: &SeedSequence) -> Xoshiro256PlusX4 {
+        Xoshiro256PlusX4::new(seeds)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn uniform(rng: &mut Xoshiro256PlusX4) -> __m256d {
+        rng.next_f64()
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn splat(value: f64) -> __m256d {
+        _mm256_set1_pd(value)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn load(lanes: &[f64; 4]) -> __m256d {
+        _mm256_loadu_pd(lanes.as_ptr())
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn store(self) -> [f64; 4] {
+        let mut lanes = [0.0; 4];
+        _mm256_storeu_pd(lanes.as_mut_ptr(), self);
+        lanes
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn add(self, other: __m256d) -> __m256d {
+        _mm256_add_pd(self, other)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sub(self, other: __m256d) -> __m256d {
+        _mm256_sub_pd(self, other)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn mul(self, other: __m256d) -> __m256d {
+        _mm256_mul_pd(self, other)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn min(self, other: __m256d) -> __m256d {
+        _mm256_min_pd(self, other)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn max(self, other: __m256d) -> __m256d {
+        _mm256_max_pd(self, other)
+    }
+}
+
+impl Lanes for __m512d {
+    type Array = [f64; 8];
+    type Rng = Xoshiro256PlusX8;
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn new_rng(seeds: &SeedSequence) -> Xoshiro256PlusX8 {
+        Xoshiro256PlusX8::new(seeds)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn uniform(rng: &mut Xoshiro256PlusX8) -> __m512d {
+        rng.next_f64()
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn splat(value: f64) -> __m512d {
+        _mm512_set1_pd(value)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn load(lanes: &[f64; 8]) -> __m512d {
+        _mm512_loadu_pd(lanes.as_ptr())
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn store(self) -> [f64; 8] {
+        let mut lanes = [0.0; 8];
+        _mm512_storeu_pd(lanes.as_mut_ptr(), self);
+        lanes
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn add(self, other: __m512d) -> __m512d {
+        _mm512_add_pd(self, other)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn sub(self, other: __m512d) -> __m512d {
+        _mm512_sub_pd(self, other)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn mul(self, other: __m512d) -> __m512d {
+        _mm512_mul_pd(self, other)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn min(self, other: __m512d) -> __m512d {
+        _mm512_min_pd(self, other)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn max(self, other: __m512d) -> __m512d {
+        _mm512_max_pd(self, other)
+    }
+}
+
+/// Draws one trial per lane into `sorted`.
+///
+/// Insertion network: every lane keeps its points sorted across `sorted`, so min/max swaps
+/// replace branching.
+#[inline(always)]
+unsafe fn sort_trials<V: Lanes>(
+    rng: &mut V::Rng,
+    sorted: &mut [V],
+    distribution: &Distribution,
+    transform: bool,
+) {
+    for filled in 0..sorted.len() {
+        let mut point = V::uniform(rng);
+
+        for slot in sorted[..filled].iter_mut() {
+            let min_vec = slot.min(point);
+            point = slot.max(point);
+            *slot = min_vec;
+        }
+        sorted[filled] = point;
+    }
+    // The quantile function is increasing, so it maps sorted uniforms to sorted points
+    if transform {
+        for value in sorted.iter_mut() {
+            let mut lanes = value.store();
+            for lane in lanes.as_mut() {
+                *lane = distribution
+                    .quantile(*lane)
+                    .expect("SIMD kernels only get distributions with a quantile function");
+            }
+            *value = V::load(&lanes);
+        }
+    }
+}
+
+/// `measure` of every lane, with the same operations as [`Measure::evaluate`].
+#[inline(always)]
+unsafe fn measure_lanes<V: Lanes>(measure: Measure, sorted: &[V], distance_weights: &[V]) -> V {
+    let n = sorted.len();
+    match measure {
+        Measure::Range => sorted[n - 1].sub(sorted[0]),
+        Measure::Midrange => sorted[0].add(sorted[n - 1]).mul(V::splat(0.5)),
+        Measure::Distance => sorted
+            .iter()
+            .zip(distance_weights)
+            .fold(V::splat(0.0), |acc, (point, weight)| {
+                acc.add(weight.mul(*point))
+            }),
+        Measure::Median => sorted[n / 2],
+        Measure::Gap(k) => sorted[k].sub(sorted[k - 1]),
+    }
+}
+
+/// Running sums of one statistic, lane by lane.
+#[derive(Clone, Copy)]
+struct LaneSums<V> {
+    sum: V,
+    sq_sum: V,
+    // Rounding errors of the two sums; only touched when compensated
+    error: V,
+    sq_error: V,
+}
+
+impl<V: Lanes> LaneSums<V> {
+    #[inline(always)]
+    unsafe fn new() -> Self {
+        let zero = V::splat(0.0);
+        LaneSums {
+            sum: zero,
+            sq_sum: zero,
+            error: zero,
+            sq_error: zero,
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn add<const COMPENSATED: bool>(&mut self, value: V) {
+        let square = value.mul(value);
+        if COMPENSATED {
+            let (sum, error) = two_sum(self.sum, value);
+            self.sum = sum;
+            self.error = self.error.add(error);
+            let (sq_sum, sq_error) = two_sum(self.sq_sum, square);
+            self.sq_sum = sq_sum;
+            self.sq_error = self.sq_error.add(sq_error);
+        } else {
+            self.sum = self.sum.add(value);
+            self.sq_sum = self.sq_sum.add(square);
+        }
+    }
+
+    /// Adds every lane to the totals of one statistic.
+    #[inline(always)]
+    unsafe fn reduce(self, total: &mut CompensatedSum, sq_total: &mut CompensatedSum) {
+        let lanes = |sum: V, error: V| {
+            let (sums, errors) = (sum.store(), error.store());
+            (0..sums.as_ref().len()).map(move |lane| CompensatedSum {
+                sum: sums.as_ref()[lane],
+                compensation: errors.as_ref()[lane],
+            })
+        };
+        for lane in lanes(self.sum, self.error) {
+            total.merge(&lane);
+        }
+        for lane in lanes(self.sq_sum, self.sq_error) {
+            sq_total.merge(&lane);
+        }
+    }
+}
+
+/// TwoSum on every lane: the rounded sums and their exact rounding errors.
+#[inline(always)]
+unsafe fn two_sum<V: Lanes>(a: V, b: V) -> (V, V) {
+    let sum = a.add(b);
+    let b_virtual = sum.sub(a);
+    let a_virtual = sum.sub(b_virtual);
+    let error = a.sub(a_virtual).add(b.sub(b_virtual));
+    (sum, error)
+}
+
+#[inline(always)]
+unsafe fn simulate_points<V: Lanes, const COMPENSATED: bool>(
+    num_simulations: u64,
+    num_points: usize,
+    mut result: SimulationResult,
+    seeds: &SeedSequence,
+    distribution: &Distribution,
+) -> SimulationResult {
+    let accumulation = if COMPENSATED {
+        Accumulation::Compensated
+    } else {
+        Accumulation::Naive
+    };
+    let width = V::Array::default().as_ref().len();
+    let mut rng = V::new_rng(seeds);
+    let measures = result.measures.clone();
+    let mut histograms = result.histograms.take();
+    let transform = !distribution.is_standard_uniform();
+    let shifted = result.shift != 0.0;
+    let shift = V::splat(result.shift);
+
+    let iterations = num_simulations / width as u64;
+    let remainder = (num_simulations % width as u64) as usize;
+
+    let mut sorted = vec![V::splat(0.0); num_points];
+    let mut order_sums = vec![LaneSums::<V>::new(); num_points];
+    let mut measure_sums = vec![LaneSums::<V>::new(); measures.len()];
+    let distance_weights: Vec<V> = (0..num_points)
+        .map(|i| V::splat(distance_weight(i, num_points)))
+        .collect();
+
+    for _ in 0..iterations {
+        sort_trials(&mut rng, &mut sorted, distribution, transform);
+        if let Some(histograms) = &mut histograms {
+            let (minima, maxima) = (sorted[0].store(), sorted[num_points - 1].store());
+            for (minimum, maximum) in minima.as_ref().iter().zip(maxima.as_ref()) {
+                histograms.add(*minimum, *maximum);
+            }
+        }
+
+        // The measures are linear, so those of the shifted points carry their own shift
+        if shifted {
+            for value in sorted.iter_mut() {
+                *value = value.sub(shift);
+            }
+        }
+        for (sums, value) in order_sums.iter_mut().zip(&sorted) {
+            sums.add::<COMPENSATED>(*value);
+        }
+        for (sums, measure) in measure_sums.iter_mut().zip(&measures) {
+            sums.add::<COMPENSATED>(measure_lanes(*measure, &sorted, &distance_weights));
+        }
+    }
+
+    result.count = iterations * width as u64;
+    result.histograms = histograms;
+    let totals = result
+        .order_sums
+        .iter_mut()
+        .zip(result.order_sq_sums.iter_mut())
+        .zip(&order_sums)
+        .chain(
+            result
+                .measure_sums
+                .iter_mut()
+                .zip(result.measure_sq_sums.iter_mut())
+                .zip(&measure_sums),
+        );
+    for ((total, sq_total), sums) in totals {
+        sums.reduce(total, sq_total);
+    }
+
+    // Handle remaining simulations with one more vector of trials, keeping only the first lanes
+    if remainder > 0 {
+        sort_trials(&mut rng, &mut sorted, distribution, transform);
+        let trials: Vec<V::Array> = sorted.iter().map(|value| value.store()).collect();
+        let mut points = vec![0.0; num_points];
+        for lane in 0..remainder {
+            for (point, trial) in points.iter_mut().zip(&trials) {
+                *point = trial.as_ref()[lane];
+            }
+            result.add_trial(&points, accumulation);
+        }
+    }
+
+    result
+}
+
+/// Four trials at a time on AVX2.
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn simulate_points_avx2<const COMPENSATED: bool>(
+    num_simulations: u64,
+    num_points: usize,
+    result: SimulationResult,
+    seeds: &SeedSequence,
+    distribution: &Distribution,
+) -> SimulationResult {
+    simulate_points::<__m256d, COMPENSATED>(
+        num_simulations,
+        num_points,
+        result,
+        seeds,
+        distribution,
+    )
+}
+
+/// Eight trials at a time on AVX-512.
+#[target_feature(enable = "avx512f")]
+pub(crate) unsafe fn simulate_points_avx512<const COMPENSATED: bool>(
+    num_simulations: u64,
+    num_points: usize,
+    result: SimulationResult,
+    seeds: &SeedSequence,
+    distribution: &Distribution,
+) -> SimulationResult {
+    simulate_points::<__m512d, COMPENSATED>(
+        num_simulations,
+        num_points,
+        result,
+        seeds,
+        distribution,
+    )
+}
@@ -0,0 +1,444 @@
+use crate::distribution::Distribution;
+use crate::error::Error;
+use crate::histogram::ExtremeHistograms;
+use crate::kernel::Kernel;
+use crate::result::{Accumulation, Estimate, SimulationResult};
+use crate::rng::{Philox4x32, RngKind, SeedSequence};
+use crate::statistic::{Measure, Statistic};
+use crate::theory::{expected_measure, order_statistic_moments};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default trials per work block; every block draws from its own stream derived from the master seed.
+pub const BLOCK_SIZE: u64 = 1 << 20;
+
+/// Blocks per worker that may be started past the oldest block not yet merged. Enough to keep
+/// every worker busy behind a slow block while bounding the results waiting to be merged.
+const REORDER_BLOCKS_PER_THREAD: u64 = 4;
+
+/// Precision at which a precision-targeted run stops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    /// Every statistic's standard error is at most this value
+    StdError(f64),
+    /// Every statistic's standard error is at most this fraction of its mean
+    Relative(f64),
+}
+
+impl Precision {
+    /// Whether every targeted statistic meets the target; `targeted` is as returned by
+    /// [`SimulationConfig::precision_targets`].
+    pub fn is_met(self, estimates: &[Estimate], targeted: &[bool]) -> bool {
+        estimates
+            .iter()
+            .zip(targeted)
+            .filter(|&(_, &targeted)| targeted)
+            .all(|(estimate, _)| match self {
+                Precision::StdError(target) => estimate
+                    .std_error
+                    .is_some_and(|std_error| std_error <= target),
+                Precision::Relative(target) => estimate
+                    .std_error
+                    .is_some_and(|std_error| std_error <= target * estimate.mean.abs()),
+            })
+    }
+
+    /// Samples needed to reach the target, extrapolated from the current estimates.
+    fn required_samples(self, estimates: &[Estimate], targeted: &[bool], count: u64) -> u64 {
+        estimates
+            .iter()
+            .zip(targeted)
+            .filter(|&(_, &targeted)| targeted)
+            .map(|(estimate, _)| {
+                let target = match self {
+                    Precision::StdError(target) => target,
+                    Precision::Relative(target) => target * estimate.mean.abs(),
+                };
+                // std_error scales as 1 / sqrt(n); without one yet, ask for as many as allowed
+                estimate.std_error.map_or(u64::MAX, |std_error| {
+                    let ratio = std_error / target;
+                    (count as f64 * ratio * ratio).ceil().min(u64::MAX as f64) as u64
+                })
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Whether a statistic with these exact moments can ever reach the target: standard errors
+    /// only shrink for a finite variance, and relative ones only around a mean other than 0.
+    fn is_reachable(self, mean: Option<f64>, finite_variance: bool, spread: f64) -> bool {
+        finite_variance
+            && match self {
+                Precision::StdError(_) => true,
+                // The means are integrated to about 1e-12 of the spread, so smaller ones are 0
+                Precision::Relative(_) => mean.is_some_and(|mean| mean.abs() > 1e-9 * spread),
+            }
+    }
+}
+
+/// Work done by one thread of the pool.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WorkerStats {
+    pub blocks: u64,
+    pub samples: u64,
+    /// Time spent inside the kernel, excluding scheduling and idle time
+    pub busy_seconds: f64,
+}
+
+impl WorkerStats {
+    pub fn samples_per_second(&self) -> f64 {
+        self.samples as f64 / self.busy_seconds
+    }
+}
+
+/// Merged statistics of a run together with how the work was spread over the pool.
+#[derive(Clone, Debug)]
+pub struct SimulationRun {
+    pub result: SimulationResult,
+    /// Indexed by the worker's position in the pool
+    pub workers: Vec<WorkerStats>,
+}
+
+impl SimulationRun {
+    /// Ratio of the busiest worker's kernel time to the least busy one's; `None` if some worker
+    /// got no blocks at all.
+    pub fn imbalance(&self) -> Option<f64> {
+        let busy = self.workers.iter().map(|worker| worker.busy_seconds);
+        let slowest = busy.clone().fold(0.0, f64::max);
+        let fastest = busy.fold(f64::INFINITY, f64::min);
+        (fastest > 0.0).then(|| slowest / fastest)
+    }
+
+    // Blocks arrive in block order, which keeps the merged result independent of the pool size
+    fn record(&mut self, block: BlockRun) {
+        let worker = &mut self.workers[block.worker];
+        worker.blocks += 1;
+        worker.samples += block.result.count;
+        worker.busy_seconds += block.seconds;
+        self.result.merge(&block.result);
+    }
+}
+
+/// Threads this process may actually run on.
+///
+/// On Linux this honours the CPU affinity mask and cgroup CPU quotas, not just the core count.
+pub fn available_threads() -> u64 {
+    std::thread::available_parallelism().map_or(1, |threads| threads.get() as u64)
+}
+
+/// Parameters of a simulation run.
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    /// Trials to run, or the upper bound on trials when `precision` or `time_limit` is set
+    pub total_simulations: u64,
+    /// Size of the worker pool
+    pub num_threads: u64,
+    pub num_points: usize,
+    /// Master seed every block's RNG stream is derived from
+    pub seed: u64,
+    pub kernel: Kernel,
+    /// Generator of the scalar kernel; `None` means the kernel's own generator
+    pub rng: Option<RngKind>,
+    /// Law of the points; the SIMD kernels need one with a closed-form quantile function
+    pub distribution: Distribution,
+    /// Statistics accumulated next to the order statistics, in the order they are reported
+    pub statistics: Vec<Statistic>,
+    /// Bins of the histograms of every trial's minimum and maximum; `None` bins nothing
+    pub histogram_bins: Option<usize>,
+    /// Stop as soon as every statistic reaches this precision
+    pub precision: Option<Precision>,
+    /// Wall-clock budget; workers stop starting new blocks once it is spent
+    pub time_limit: Option<Duration>,
+    /// Trials per work block, the unit the pool schedules. Blocks get their own RNG streams, so
+    /// unlike the pool size this does change the numbers a seed produces.
+    pub block_size: u64,
+    pub accumulation: Accumulation,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            total_simulations: 100_000_000,
+            num_threads: 1,
+            num_points: 2,
+            seed: 0,
+            kernel: Kernel::detect(),
+            rng: None,
+            distribution: Distribution::default(),
+            statistics: Vec::new(),
+            histogram_bins: None,
+            precision: None,
+            time_limit: None,
+            block_size: BLOCK_SIZE,
+            accumulation: Accumulation::Naive,
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// Name of the generator the points are drawn from.
+    pub fn rng_name(&self) -> &'static str {
+        self.rng
+            .map_or_else(|| self.kernel.native_rng_name(), RngKind::name)
+    }
+
+    /// Values every trial adds for the selected statistics.
+    pub fn measures(&self) -> Vec<Measure> {
+        self.statistics
+            .iter()
+            .flat_map(|statistic| statistic.measures(self.num_points))
+            .collect()
+    }
+
+    /// Result with nothing added yet, but room for everything this configuration accumulates.
+    pub fn empty_result(&self) -> SimulationResult {
+        let mut result = SimulationResult::with_measures(self.num_points, self.measures());
+        // Squares of points far from 0 next to their spread leave few bits for the variance,
+        // so those are summed relative to their centre. Laws near 0 keep a zero shift, which
+        // the SIMD kernels skip.
+        let (centre, spread) = self.distribution.location_scale();
+        if centre.abs() > 4.0 * spread {
+            result.shift = centre;
+        }
+        result.histograms = self
+            .histogram_bins
+            .map(|bins| ExtremeHistograms::new(&self.distribution, self.num_points, bins));
+        result
+    }
+
+    /// Whether the precision target can be reached for each statistic, in the order of
+    /// [`SimulationResult::estimates`]; every statistic counts when there is no target.
+    ///
+    /// Cauchy extremes have no finite variance, and the mean of a symmetric law's middle point
+    /// is 0, so no number of trials gets their standard errors to such targets. They are left
+    /// out rather than running up to the simulation cap.
+    pub fn precision_targets(&self) -> Vec<bool> {
+        let num_points = self.num_points;
+        let Some(precision) = self.precision else {
+            return vec![true; num_points + self.measures().len()];
+        };
+        let moments: Vec<_> = (1..=num_points)
+            .map(|k| order_statistic_moments(&self.distribution, k, num_points))
+            .collect();
+        let finite_variance =
+            |i: usize| moments[i].is_some_and(|moments| moments.variance.is_finite());
+        let spread = self.distribution.location_scale().1;
+        let order_statistics = moments.iter().enumerate().map(|(i, moments)| {
+            precision.is_reachable(
+                moments.map(|moments| moments.mean),
+                finite_variance(i),
+                spread,
+            )
+        });
+        let measures = self.measures().into_iter().map(|measure| {
+            let weights = measure.weights(num_points);
+            precision.is_reachable(
+                expected_measure(&self.distribution, measure, num_points),
+                weights.iter().all(|&(i, _)| finite_variance(i)),
+                spread,
+            )
+        });
+        order_statistics.chain(measures).collect()
+    }
+
+    /// Trials of `block`, numbered from the start of the run.
+    pub fn block_trials(&self, block: u64) -> Range<u64> {
+        let start = block * self.block_size;
+        start
+            ..self
+                .total_simulations
+                .min(start.saturating_add(self.block_size))
+    }
+
+    /// Seeds of the sequential generators of `block`.
+    pub fn block_seeds(&self, block: u64) -> SeedSequence {
+        SeedSequence::new(u128::from(self.seed), &[block])
+    }
+
+    /// Counter-based generator shared by all blocks; trials pick their own counters.
+    pub fn philox(&self) -> Philox4x32 {
+        SeedSequence::new(u128::from(self.seed), &[]).seed_rng()
+    }
+}
+
+/// Runs the configured simulation on a pool of `num_threads` work-stealing workers.
+///
+/// The result depends only on the seed, the point count, the block size and the kernel, never on
+/// the thread count.
+/// With a precision target the blocks run in rounds whose sizes depend only on the merged
+/// estimates, so precision-targeted runs are just as reproducible. A time limit trades that
+/// away: the number of blocks finished depends on the machine, and the result counts only those.
+pub fn parallel_simulate(config: &SimulationConfig) -> Result<SimulationRun, Error> {
+    if !config.kernel.is_supported() {
+        return Err(Error::UnsupportedKernel(config.kernel));
+    }
+    if config.rng.is_some() && config.kernel != Kernel::Scalar {
+        return Err(Error::InvalidConfig(
+            "only the scalar kernel can draw from a selectable generator",
+        ));
+    }
+    config.distribution.check()?;
+    if config.kernel != Kernel::Scalar && config.distribution.quantile(0.5).is_none() {
+        return Err(Error::InvalidConfig(
+            "only the scalar kernel can sample distributions without a closed-form quantile",
+        ));
+    }
+    if config.num_threads == 0 {
+        return Err(Error::InvalidConfig("the pool needs at least 1 thread"));
+    }
+    if config.num_points == 0 {
+        return Err(Error::InvalidConfig("a trial needs at least 1 point"));
+    }
+    if config.block_size == 0 {
+        return Err(Error::InvalidConfig("block size must be at least 1"));
+    }
+    if config.histogram_bins == Some(0) {
+        return Err(Error::InvalidConfig("a histogram needs at least 1 bin"));
+    }
+    for statistic in &config.statistics {
+        statistic.check(config.num_points)?;
+    }
+    let targeted = config.precision_targets();
+    if !targeted.contains(&true) {
+        return Err(Error::InvalidConfig(
+            "no statistic can reach the precision target",
+        ));
+    }
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(config.num_threads as usize)
+        .build()?;
+
+    let deadline = config.time_limit.map(|limit| Instant::now() + limit);
+    let total_blocks = config.total_simulations.div_ceil(config.block_size);
+    let mut run = SimulationRun {
+        result: config.empty_result(),
+        workers: vec![WorkerStats::default(); pool.current_num_threads()],
+    };
+    let Some(precision) = config.precision else {
+        simulate_blocks(config, &pool, 0..total_blocks, deadline, &mut run);
+        return Ok(run);
+    };
+
+    let mut next_block = 0;
+    let mut round_blocks = 1;
+    while next_block < total_blocks && deadline.is_none_or(|deadline| Instant::now() < deadline) {
+        let end = total_blocks.min(next_block + round_blocks);
+        simulate_blocks(config, &pool, next_block..end, deadline, &mut run);
+        next_block = end;
+
+        let total = &run.result;
+        let estimates = total.estimates();
+        if precision.is_met(&estimates, &targeted) {
+            break;
+        }
+        // Aim straight for the extrapolated sample count, but at most double per round so a
+        // noisy early variance estimate cannot overshoot by much
+        let missing = precision
+            .required_samples(&estimates, &targeted, total.count)
+            .saturating_sub(total.count);
+        round_blocks = missing.div_ceil(config.block_size).clamp(1, next_block);
+    }
+
+    Ok(run)
+}
+
+/// One finished block and where it ran.
+struct BlockRun {
+    result: SimulationResult,
+    worker: usize,
+    seconds: f64,
+}
+
+/// Runs `blocks` on `pool` and records their results in `run`, in block order.
+///
+/// Every worker takes the next block as soon as it is free, so faster cores simply take more of
+/// them and no worker waits for another's block to finish. Finished blocks are merged on the
+/// calling thread, reordered by block index, so the reduction, and hence the result, is
+/// independent of the pool size. Workers check `deadline` before every block, so past it only
+/// the blocks already finished are recorded.
+fn simulate_blocks(
+    config: &SimulationConfig,
+    pool: &ThreadPool,
+    blocks: Range<u64>,
+    deadline: Option<Instant>,
+    run: &mut SimulationRun,
+) {
+    let num_workers = (pool.current_num_threads() as u64).min(blocks.end - blocks.start);
+    // Every finished block holds its own measures and histograms until it is merged, so workers
+    // stay within a window past the oldest unmerged block
+    let window = REORDER_BLOCKS_PER_THREAD * pool.current_num_threads() as u64;
+    let next_block = AtomicU64::new(blocks.start);
+    let merged = (Mutex::new(blocks.start), Condvar::new());
+    let (sender, receiver) = mpsc::channel::<(u64, Option<BlockRun>)>();
+
+    pool.in_place_scope(|scope| {
+        for _ in 0..num_workers {
+            let sender = sender.clone();
+            let (next_block, merged) = (&next_block, &merged);
+            scope.spawn(move |_| loop {
+                let block = next_block.fetch_add(1, Ordering::Relaxed);
+                if block >= blocks.end {
+                    return;
+                }
+                let (oldest, merging) = merged;
+                drop(
+                    merging
+                        .wait_while(oldest.lock().unwrap(), |oldest| block >= *oldest + window)
+                        .unwrap(),
+                );
+                // Answer every block taken, so the merge knows not to wait for skipped ones
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    let _ = sender.send((block, None));
+                    return;
+                }
+                let start_time = Instant::now();
+                let result = config.kernel.simulate(config, block);
+                let finished = BlockRun {
+                    result,
+                    worker: rayon::current_thread_index().unwrap_or(0),
+                    seconds: start_time.elapsed().as_secs_f64(),
+                };
+                let _ = sender.send((block, Some(finished)));
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut oldest = blocks.start;
+        for (block, finished) in receiver {
+            pending.insert(block, finished);
+            while let Some(finished) = pending.remove(&oldest) {
+                if let Some(finished) = finished {
+                    run.record(finished);
+                }
+                oldest += 1;
+            }
+            *merged.0.lock().unwrap() = oldest;
+            merged.1.notify_all();
+        }
+    });
+}
+
+/// The points of trial `trial` of a counter-based run, in the order they were drawn.
+///
+/// Only runs with [`RngKind::Philox`] give every trial its own counter, so only they can be
+/// replayed without rerunning everything before the trial.
+pub fn replay_trial(config: &SimulationConfig, trial: u64) -> Result<Vec<f64>, Error> {
+    if config.rng != Some(RngKind::Philox) {
+        return Err(Error::InvalidConfig(
+            "only runs with the philox generator can replay single trials",
+        ));
+    }
+    // The same draws the kernel makes for this trial, before it sorts them
+    config.distribution.check()?;
+    let sampler = config.distribution.sampler();
+    let mut rng = config.philox();
+    rng.seek_trial(trial);
+    Ok((0..config.num_points)
+        .map(|_| sampler.sample(&mut rng))
+        .collect())
+}
@@ -0,0 +1,139 @@
+use crate::error::Error;
+
+/// Statistic of each trial's sorted points that a run can accumulate next to the order
+/// statistics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Statistic {
+    /// Maximum minus minimum
+    Range,
+    /// Mean of the minimum and the maximum
+    Midrange,
+    /// `|X - Y|` averaged over every pair of points; for two points simply `|X - Y|`
+    Distance,
+    /// Middle point of an odd number of points
+    Median,
+    /// Every distance between neighbouring sorted points
+    Gaps,
+}
+
+impl Statistic {
+    pub fn from_name(name: &str) -> Option<Statistic> {
+        match name {
+            "range" => Some(Statistic::Range),
+            "midrange" => Some(Statistic::Midrange),
+            "distance" => Some(Statistic::Distance),
+            "median" => Some(Statistic::Median),
+            "gaps" => Some(Statistic::Gaps),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Statistic::Range => "range",
+            Statistic::Midrange => "midrange",
+            Statistic::Distance => "distance",
+            Statistic::Median => "median",
+            Statistic::Gaps => "gaps",
+        }
+    }
+
+    pub fn applies_to(self, num_points: usize) -> bool {
+        match self {
+            Statistic::Median => num_points % 2 == 1,
+            _ => num_points >= 2,
+        }
+    }
+
+    pub fn check(self, num_points: usize) -> Result<(), Error> {
+        match self {
+            _ if self.applies_to(num_points) => Ok(()),
+            Statistic::Median => Err(Error::InvalidConfig(
+                "the median needs an odd number of points",
+            )),
+            _ => Err(Error::InvalidConfig(
+                "range, midrange, distance and gaps need at least 2 points",
+            )),
+        }
+    }
+
+    /// The values this statistic adds to every trial: one, or `num_points - 1` for the gaps.
+    pub fn measures(self, num_points: usize) -> Vec<Measure> {
+        match self {
+            Statistic::Range => vec![Measure::Range],
+            Statistic::Midrange => vec![Measure::Midrange],
+            Statistic::Distance => vec![Measure::Distance],
+            Statistic::Median => vec![Measure::Median],
+            Statistic::Gaps => (1..num_points).map(Measure::Gap).collect(),
+        }
+    }
+}
+
+/// One value accumulated per trial for a selected [`Statistic`].
+///
+/// Every measure is a fixed linear combination of the sorted points, see [`Measure::weights`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Measure {
+    Range,
+    Midrange,
+    Distance,
+    Median,
+    /// `X_(k+1) - X_(k)`, counting k from 1
+    Gap(usize),
+}
+
+impl Measure {
+    pub fn label(self) -> String {
+        match self {
+            Measure::Range => "range".to_string(),
+            Measure::Midrange => "midrange".to_string(),
+            Measure::Distance => "distance".to_string(),
+            Measure::Median => "median".to_string(),
+            Measure::Gap(k) => format!("gap {}", k),
+        }
+    }
+
+    /// The measure of one trial, from its points in ascending order.
+    #[inline]
+    pub fn evaluate(self, sorted: &[f64]) -> f64 {
+        let n = sorted.len();
+        match self {
+            Measure::Range => sorted[n - 1] - sorted[0],
+            Measure::Midrange => (sorted[0] + sorted[n - 1]) * 0.5,
+            Measure::Distance => sorted
+                .iter()
+                .enumerate()
+                .fold(0.0, |acc, (i, point)| acc + distance_weight(i, n) * point),
+            Measure::Median => sorted[n / 2],
+            Measure::Gap(k) => sorted[k] - sorted[k - 1],
+        }
+    }
+
+    /// Sum of the weights: moving every point by c moves the measure by c times this.
+    pub fn weight_sum(self) -> f64 {
+        match self {
+            Measure::Midrange | Measure::Median => 1.0,
+            Measure::Range | Measure::Distance | Measure::Gap(_) => 0.0,
+        }
+    }
+
+    /// `(index, weight)` pairs with `measure = sum of weight * sorted[index]`.
+    pub fn weights(self, num_points: usize) -> Vec<(usize, f64)> {
+        let n = num_points;
+        match self {
+            Measure::Range => vec![(n - 1, 1.0), (0, -1.0)],
+            Measure::Midrange => vec![(0, 0.5), (n - 1, 0.5)],
+            Measure::Distance => (0..n).map(|i| (i, distance_weight(i, n))).collect(),
+            Measure::Median => vec![(n / 2, 1.0)],
+            Measure::Gap(k) => vec![(k, 1.0), (k - 1, -1.0)],
+        }
+    }
+}
+
+/// Weight of the `(i + 1)`-th smallest of `n` points in their mean pairwise distance.
+///
+/// It is the larger point of i pairs and the smaller one of `n - 1 - i`, out of `n (n - 1) / 2`.
+#[inline]
+pub(crate) fn distance_weight(i: usize, n: usize) -> f64 {
+    2.0 * (2.0 * i as f64 - (n - 1) as f64) / (n * (n - 1)) as f64
+}
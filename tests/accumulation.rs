@@ -0,0 +1,149 @@
+mod common;
+
+use montecarlo::{
+    parallel_simulate, Accumulation, CompensatedSum, Distribution, SimulationConfig,
+    SimulationResult,
+};
+
+// 0.1 is not representable, so every naive addition rounds; ten million of them drift far
+// enough to show, while the compensated sum stays at the correctly rounded result.
+#[test]
+fn compensated_sum_is_accurate_where_naive_sum_drifts() {
+    let terms = 10_000_000;
+    let exact = 1_000_000.0;
+
+    let mut naive = 0.0_f64;
+    let mut compensated = CompensatedSum::default();
+    for _ in 0..terms {
+        naive += 0.1;
+        compensated.add(0.1);
+    }
+
+    assert!(
+        (naive - exact).abs() > 1e-6,
+        "naive error {}",
+        naive - exact
+    );
+    assert!(
+        (compensated.value() - exact).abs() <= f64::EPSILON * exact,
+        "compensated error {}",
+        compensated.value() - exact
+    );
+}
+
+#[test]
+fn accumulation_modes_agree_on_the_same_samples() {
+    for kernel in common::supported_kernels() {
+        let config = SimulationConfig {
+            total_simulations: 1_000_000,
+            num_points: 3,
+            seed: 7,
+            kernel,
+            ..SimulationConfig::default()
+        };
+        let naive = parallel_simulate(&config).unwrap().result.estimates();
+        let compensated = parallel_simulate(&SimulationConfig {
+            accumulation: Accumulation::Compensated,
+            ..config
+        })
+        .unwrap()
+        .result
+        .estimates();
+
+        for (naive, compensated) in naive.iter().zip(&compensated) {
+            assert!((naive.mean - compensated.mean).abs() < 1e-12);
+        }
+    }
+}
+
+// A naive lane only sums the trials of one block, and blocks are merged with compensation, so
+// the block size bounds the naive rounding error however many trials run
+#[test]
+fn naive_error_grows_with_the_block_size_only() {
+    for kernel in common::supported_kernels() {
+        let relative_error = |block_size| {
+            let config = SimulationConfig {
+                total_simulations: 1 << 22,
+                num_points: 2,
+                seed: 11,
+                kernel,
+                block_size,
+                ..SimulationConfig::default()
+            };
+            let naive = parallel_simulate(&config).unwrap().result;
+            let compensated = parallel_simulate(&SimulationConfig {
+                accumulation: Accumulation::Compensated,
+                ..config
+            })
+            .unwrap()
+            .result;
+            let sums = |result: &SimulationResult| {
+                result
+                    .order_sums
+                    .iter()
+                    .chain(&result.order_sq_sums)
+                    .map(CompensatedSum::value)
+                    .collect::<Vec<_>>()
+            };
+            sums(&naive)
+                .iter()
+                .zip(sums(&compensated))
+                .map(|(naive, exact)| ((naive - exact) / exact).abs())
+                .fold(0.0, f64::max)
+        };
+
+        let small = relative_error(1 << 12);
+        let large = relative_error(1 << 22);
+        // At most an ulp per sum against more than ten
+        assert!(
+            small <= f64::EPSILON,
+            "{:?} blocks of 2^12: {:e}",
+            kernel,
+            small
+        );
+        assert!(
+            large > 10.0 * f64::EPSILON,
+            "{:?} one block of 2^22: {:e}",
+            kernel,
+            large
+        );
+    }
+}
+
+// Raw sums of squares of points near 1e8 cancel to nothing, so the kernels sum them relative
+// to the law's centre instead
+#[test]
+fn variances_keep_their_precision_far_from_zero() {
+    for kernel in common::supported_kernels() {
+        for accumulation in [Accumulation::Naive, Accumulation::Compensated] {
+            let config = SimulationConfig {
+                total_simulations: 100_003,
+                num_points: 1,
+                seed: 3,
+                kernel,
+                distribution: Distribution::Normal {
+                    mean: 1e8,
+                    std_dev: 1.0,
+                },
+                accumulation,
+                ..SimulationConfig::default()
+            };
+            let estimate = parallel_simulate(&config).unwrap().result.estimates()[0];
+            let variance = estimate.variance.unwrap();
+            // The sample variance of 1e5 standard normals is within 1% of 1 with high probability
+            assert!(
+                (variance - 1.0).abs() < 0.02,
+                "{:?} {:?}: variance {}",
+                kernel,
+                accumulation,
+                variance
+            );
+            common::assert_within_five_sigma(
+                estimate.mean,
+                1e8,
+                estimate.std_error.unwrap(),
+                format!("{:?} {:?} mean", kernel, accumulation),
+            );
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use montecarlo::{
+    Accumulation, BenchBaseline, BenchResult, Distribution, Error, Kernel, RngKind,
+    SimulationConfig, Statistic,
+};
+
+fn result(threads: u64, samples_per_second: f64) -> BenchResult {
+    BenchResult {
+        threads,
+        samples: 1_000_000,
+        mean_seconds: 1e6 / samples_per_second,
+        stddev_seconds: 0.0,
+        min_seconds: 1e6 / samples_per_second,
+        max_seconds: 1e6 / samples_per_second,
+        samples_per_second,
+    }
+}
+
+#[test]
+fn baselines_only_compare_like_with_like() {
+    let config = SimulationConfig {
+        kernel: Kernel::Scalar,
+        ..SimulationConfig::default()
+    };
+    let baseline = BenchBaseline::new(&config, &[result(1, 100e6), result(2, 200e6)]);
+
+    let comparisons = baseline
+        .compare(&config, &[result(2, 150e6), result(4, 400e6)])
+        .unwrap();
+    assert_eq!(comparisons.len(), 1);
+    assert_eq!(comparisons[0].threads, 2);
+    assert!((comparisons[0].change + 0.25).abs() < 1e-12);
+    assert!(comparisons[0].is_regression(0.2));
+    assert!(!comparisons[0].is_regression(0.3));
+
+    // Nothing to compare with, which must not pass as free of regressions
+    assert!(baseline.compare(&config, &[result(4, 400e6)]).is_err());
+    // Throughput of another workload is no reference
+    let others = [
+        SimulationConfig {
+            num_points: 3,
+            ..config.clone()
+        },
+        SimulationConfig {
+            kernel: Kernel::Avx2,
+            ..config.clone()
+        },
+        SimulationConfig {
+            rng: Some(RngKind::ChaCha8),
+            ..config.clone()
+        },
+        SimulationConfig {
+            distribution: Distribution::Exponential { rate: 1.0 },
+            ..config.clone()
+        },
+        SimulationConfig {
+            accumulation: Accumulation::Compensated,
+            ..config.clone()
+        },
+        SimulationConfig {
+            statistics: vec![Statistic::Range],
+            ..config.clone()
+        },
+        SimulationConfig {
+            histogram_bins: Some(10),
+            ..config.clone()
+        },
+        SimulationConfig {
+            block_size: 1 << 10,
+            ..config.clone()
+        },
+    ];
+    for other in &others {
+        assert!(matches!(
+            baseline.check(other, &[1]),
+            Err(Error::BaselineMismatch(_))
+        ));
+    }
+    assert!(baseline.check(&config, &[1]).is_ok());
+}
+
+#[test]
+fn baselines_of_unknown_layouts_are_rejected() {
+    let config = SimulationConfig::default();
+    let mut json = serde_json::to_value(BenchBaseline::new(&config, &[result(1, 1e6)])).unwrap();
+    let saved = BenchBaseline::read(json.to_string().as_bytes()).unwrap();
+    assert_eq!(saved.block_size, config.block_size);
+
+    json["schema_version"] = 1.into();
+    assert!(BenchBaseline::read(json.to_string().as_bytes()).is_err());
+}
@@ -0,0 +1,110 @@
+use montecarlo::available_threads;
+use serde_json::Value;
+use std::process::{Command, Output};
+
+fn montecarlo(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_montecarlo"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// Bad command lines exit with 2, failed runs with 1
+fn assert_rejected(args: &[&str]) {
+    let output = montecarlo(args);
+    assert_eq!(output.status.code(), Some(2), "{:?} was accepted", args);
+}
+
+fn json_report(args: &[&str]) -> Value {
+    let output = montecarlo(&[args, &["-f", "json"]].concat());
+    assert!(output.status.success(), "{:?} failed", args);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn counts_take_separators_exponents_and_suffixes() {
+    for (text, count) in [
+        ("1500", 1500),
+        ("1_500", 1500),
+        ("1.5e3", 1500),
+        ("1.5k", 1500),
+        ("2K", 2000),
+        ("0.002M", 2000),
+    ] {
+        assert_eq!(json_report(&["-s", text])["simulations"], count, "{}", text);
+    }
+    for text in [
+        "0",
+        "1.5",
+        "-5",
+        "inf",
+        "nan",
+        "1e30",
+        "20000000T",
+        "1x",
+        "k",
+        "",
+    ] {
+        assert_rejected(&["-s", text]);
+    }
+}
+
+#[test]
+fn durations_take_units() {
+    for (text, seconds) in [
+        ("500ms", 0.5),
+        ("0.25s", 0.25),
+        ("2", 2.0),
+        ("2m", 120.0),
+        ("1h", 3600.0),
+    ] {
+        let report = json_report(&["-s", "1000", "--time-limit", text]);
+        assert_eq!(report["time_limit_seconds"], seconds, "{}", text);
+    }
+    for text in ["0s", "-1s", "10x", "ms", "inf", "1e30h"] {
+        assert_rejected(&["--time-limit", text]);
+    }
+}
+
+#[test]
+fn point_counts_take_lists_and_ranges() {
+    let output = montecarlo(&["sweep", "-n", "1..3,5", "-s", "1000", "-f", "csv"]);
+    assert!(output.status.success());
+    let mut points: Vec<usize> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').nth(3).unwrap().parse().unwrap())
+        .collect();
+    points.dedup();
+    assert_eq!(points, [1, 2, 3, 5]);
+    for text in ["3..1", "0..2", "1..", "..2", "1,,2", "0"] {
+        assert_rejected(&["sweep", "-n", text]);
+    }
+}
+
+#[test]
+fn thread_counts_are_positive() {
+    assert_eq!(json_report(&["-s", "1000", "-t", "02"])["threads"], 2);
+    for text in ["0", "00", "-1", "two"] {
+        assert_rejected(&["-t", text]);
+    }
+}
+
+#[test]
+fn auto_uses_every_available_thread() {
+    let available = available_threads();
+    assert_eq!(
+        json_report(&["-s", "1000", "-t", "auto"])["threads"],
+        available
+    );
+    let output = montecarlo(&["scaling", "-s", "1000", "--warmup", "0", "--runs", "1"]);
+    assert!(output.status.success());
+    let table = String::from_utf8(output.stdout).unwrap();
+    let last_row = table.lines().last().unwrap();
+    assert!(
+        last_row.contains(&format!(" -t {}` |", available)),
+        "{}",
+        last_row
+    );
+}
@@ -0,0 +1,43 @@
+//! Helpers shared by the integration tests.
+
+// Every test crate compiles its own copy, and not all of them use every helper
+#![allow(dead_code)]
+
+use montecarlo::{parallel_simulate, Kernel, SimulationConfig, SimulationResult};
+use std::fmt::Display;
+
+/// The kernels this CPU can run.
+pub fn supported_kernels() -> impl Iterator<Item = Kernel> {
+    [Kernel::Scalar, Kernel::Avx2, Kernel::Avx512]
+        .into_iter()
+        .filter(|kernel| kernel.is_supported())
+}
+
+/// Runs `config` on every supported kernel and hands each run's configuration and result to
+/// `check`.
+pub fn on_every_kernel(
+    config: &SimulationConfig,
+    mut check: impl FnMut(&SimulationConfig, SimulationResult),
+) {
+    for kernel in supported_kernels() {
+        let config = SimulationConfig {
+            kernel,
+            ..config.clone()
+        };
+        let result = parallel_simulate(&config).unwrap().result;
+        check(&config, result);
+    }
+}
+
+/// Asserts that `value` is within five standard errors of `expected`, which a correct
+/// simulation misses about once in two million checks.
+pub fn assert_within_five_sigma(value: f64, expected: f64, std_error: f64, what: impl Display) {
+    assert!(
+        (value - expected).abs() < 5.0 * std_error,
+        "{}: {} against {} ± {}",
+        what,
+        value,
+        expected,
+        std_error
+    );
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use montecarlo::{expected_order_statistic, normal_quantile, Distribution, SimulationConfig};
+
+#[test]
+fn parses_distribution_specs() {
+    assert_eq!(
+        Distribution::from_spec("normal"),
+        Ok(Distribution::Normal {
+            mean: 0.0,
+            std_dev: 1.0
+        })
+    );
+    assert_eq!(
+        Distribution::from_spec(" beta(2, 5) "),
+        Ok(Distribution::Beta {
+            alpha: 2.0,
+            beta: 5.0
+        })
+    );
+    assert!(Distribution::from_spec("beta").is_err());
+    assert!(Distribution::from_spec("normal(0, -1)").is_err());
+    assert!(Distribution::from_spec("exponential(1, 2)").is_err());
+    assert!(Distribution::from_spec("pareto").is_err());
+}
+
+#[test]
+fn normal_quantile_matches_reference_values() {
+    let cases = [
+        (0.5, 0.0),
+        (0.975, 1.959_963_984_540_054),
+        (0.001, -3.090_232_306_167_813_6),
+        (1e-10, -6.361_340_902_404_056),
+    ];
+    for (p, expected) in cases {
+        assert!((normal_quantile(p) - expected).abs() < 1e-12, "p = {}", p);
+    }
+}
+
+// The SIMD kernels map sorted uniforms through the quantile; the scalar kernel samples
+// directly, so both must agree with Rényi's exact exponential expectations
+#[test]
+fn exponential_order_statistics_match_theory_on_every_kernel() {
+    let distribution = Distribution::Exponential { rate: 2.0 };
+    let config = SimulationConfig {
+        total_simulations: 200_000,
+        num_points: 3,
+        seed: 5,
+        distribution,
+        ..SimulationConfig::default()
+    };
+    common::on_every_kernel(&config, |config, result| {
+        for (k, estimate) in (1..=3).zip(result.estimates()) {
+            let expected = expected_order_statistic(&distribution, k, 3).unwrap();
+            common::assert_within_five_sigma(
+                estimate.mean,
+                expected,
+                estimate.std_error.unwrap(),
+                format!("{} kernel, k = {}", config.kernel.name(), k),
+            );
+        }
+    });
+}
@@ -0,0 +1,112 @@
+use montecarlo::{
+    bonferroni_threshold, chi_square_p_value, kolmogorov_p_value, normal_p_value,
+    parallel_simulate, verification_checks, write_verification_table, Check, ChiSquare,
+    Distribution, Histogram, KolmogorovSmirnov, Report, SimulationConfig, Statistic,
+};
+use std::time::Duration;
+
+#[test]
+fn p_values_match_reference_tables() {
+    assert!((normal_p_value(1.959_963_984_540_054) - 0.05).abs() < 1e-14);
+    assert!((normal_p_value(-2.575_829_303_548_901) - 0.01).abs() < 1e-14);
+    assert!((chi_square_p_value(3.841_458_820_694_124, 1) - 0.05).abs() < 1e-14);
+    assert!((chi_square_p_value(18.307_038_053_275_146, 10) - 0.05).abs() < 1e-14);
+    // The Kolmogorov distribution's 5% point, which Stephens' correction shifts for finite counts
+    let count = 1_000_000;
+    let root = (count as f64).sqrt();
+    let statistic = 1.358_098_639_322_550_5 / (root + 0.12 + 0.11 / root);
+    assert!((kolmogorov_p_value(statistic, count) - 0.05).abs() < 1e-9);
+    assert_eq!(kolmogorov_p_value(0.0, count), 1.0);
+}
+
+#[test]
+fn tests_tell_a_fitting_histogram_from_a_skewed_one() {
+    // Four equally likely bins, with nothing outside them
+    let edge_cdf = [0.0, 0.25, 0.5, 0.75, 1.0];
+    let mut fitting = Histogram::new(0.0, 1.0, 4);
+    let mut skewed = Histogram::new(0.0, 1.0, 4);
+    for i in 0..10_000 {
+        fitting.add((i % 4) as f64 / 4.0 + 0.1);
+        skewed.add(if i % 5 == 0 {
+            0.9
+        } else {
+            (i % 3) as f64 / 4.0
+        });
+    }
+
+    let chi_square = ChiSquare::new(&fitting, &edge_cdf).unwrap();
+    // The empty cells outside the range are pooled into their neighbours
+    assert_eq!(chi_square.degrees_of_freedom, 3);
+    assert_eq!(chi_square.statistic, 0.0);
+    assert_eq!(chi_square.p_value, 1.0);
+    assert_eq!(KolmogorovSmirnov::new(&fitting, &edge_cdf).p_value, 1.0);
+
+    assert!(ChiSquare::new(&skewed, &edge_cdf).unwrap().p_value < 1e-20);
+    assert!(KolmogorovSmirnov::new(&skewed, &edge_cdf).p_value < 1e-20);
+
+    // Too few values for two cells of 5 expected
+    let mut sparse = Histogram::new(0.0, 1.0, 4);
+    sparse.add(0.5);
+    assert!(ChiSquare::new(&sparse, &edge_cdf).is_none());
+}
+
+#[test]
+fn correct_runs_pass_and_skip_means_without_a_variance() {
+    let config = SimulationConfig {
+        total_simulations: 300_000,
+        num_points: 5,
+        seed: 21,
+        statistics: vec![Statistic::Median, Statistic::Range],
+        histogram_bins: Some(50),
+        ..SimulationConfig::default()
+    };
+    let run = parallel_simulate(&config).unwrap();
+    let report = Report::new(&config, &run, Duration::from_secs(1));
+    let checks = verification_checks(&report, &run.result.measures);
+    // Five order statistics, two measures and two tests of each of the two histograms
+    assert_eq!(checks.len(), 11);
+    for check in &checks {
+        assert!(check.p_value > 1e-4, "{} {}", check.label, check.test);
+    }
+
+    let cauchy = SimulationConfig {
+        distribution: Distribution::Cauchy {
+            location: 0.0,
+            scale: 1.0,
+        },
+        ..config
+    };
+    let run = parallel_simulate(&cauchy).unwrap();
+    let report = Report::new(&cauchy, &run, Duration::from_secs(1));
+    let checks = verification_checks(&report, &run.result.measures);
+    // Of the means, only those of the middle point and the median have a variance
+    let means: Vec<&str> = checks
+        .iter()
+        .filter(|check| check.test == "mean")
+        .map(|check| check.label.as_str())
+        .collect();
+    assert_eq!(means, ["order statistic 3", "median"]);
+}
+
+#[test]
+fn verification_holds_each_test_to_the_bonferroni_threshold() {
+    let check = |p_value| Check {
+        label: "minimum".to_string(),
+        test: "mean",
+        statistic: 0.0,
+        p_value,
+    };
+    let checks = [check(0.5), check(0.004), check(0.003)];
+    let threshold = bonferroni_threshold(0.01, checks.len());
+    let passed: Vec<bool> = checks.iter().map(|check| check.passes(threshold)).collect();
+    assert_eq!(passed, [true, true, false]);
+    let mut table = Vec::new();
+    write_verification_table(&checks, 0.01, &mut table).unwrap();
+    let results: Vec<String> = String::from_utf8(table)
+        .unwrap()
+        .lines()
+        .skip(4)
+        .map(|line| line.rsplit('|').nth(1).unwrap().trim().to_string())
+        .collect();
+    assert_eq!(results, ["pass", "pass", "FAIL"]);
+}
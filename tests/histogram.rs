@@ -0,0 +1,102 @@
+mod common;
+
+use montecarlo::{
+    order_statistic_density, parallel_simulate, Distribution, ExtremeHistograms, Histogram,
+    HistogramReport, Report, SimulationConfig,
+};
+use std::time::Duration;
+
+#[test]
+fn values_land_in_their_bins() {
+    let mut histogram = Histogram::new(0.0, 1.0, 4);
+    for value in [-0.1, 0.0, 0.2, 0.25, 0.999, 1.0, 3.0] {
+        histogram.add(value);
+    }
+    assert_eq!(histogram.counts, vec![2, 1, 0, 1]);
+    assert_eq!((histogram.below, histogram.above), (1, 2));
+
+    let mut other = Histogram::new(0.0, 1.0, 4);
+    other.add(0.6);
+    histogram.merge(&other);
+    assert_eq!(histogram.counts, vec![2, 1, 1, 1]);
+    assert_eq!(histogram.total(), 8);
+}
+
+#[test]
+fn unbounded_laws_cut_their_tails() {
+    let histograms = ExtremeHistograms::new(&Distribution::Exponential { rate: 1.0 }, 3, 10);
+    assert_eq!(histograms.minimum.low, 0.0);
+    // One maximum of three in a thousand lies above the range
+    let high = histograms.maximum.high;
+    assert!(((1.0 - (-high).exp()).powi(3) - 0.999).abs() < 1e-12);
+}
+
+#[test]
+fn every_kernel_bins_every_trial_by_the_exact_density() {
+    let config = SimulationConfig {
+        // Not a multiple of 8, so the SIMD kernels' remainder path is binned too
+        total_simulations: 400_003,
+        num_points: 2,
+        seed: 5,
+        histogram_bins: Some(20),
+        ..SimulationConfig::default()
+    };
+    common::on_every_kernel(&config, |config, result| {
+        let histograms = result.histograms.as_ref().unwrap();
+        assert_eq!(histograms.minimum.total(), result.count);
+        assert_eq!(histograms.maximum.total(), result.count);
+
+        // The minimum of two uniforms is Beta(1, 2), with density 2 (1 - x)
+        let report = HistogramReport::new(&histograms.minimum, &config.distribution, 1, 2);
+        for bin in &report.bins {
+            let centre = (bin.low + bin.high) / 2.0;
+            // Linear, so its mean over the bin is its value at the centre
+            let exact = order_statistic_density(&config.distribution, 1, 2, centre);
+            assert!((exact - 2.0 * (1.0 - centre)).abs() < 1e-12);
+            assert!((bin.theoretical_density - exact).abs() < 1e-12);
+            // Binomial standard error of the bin's count, as a density
+            let probability = bin.theoretical_density * (bin.high - bin.low);
+            let std_error = (probability * (1.0 - probability) / result.count as f64).sqrt()
+                / (bin.high - bin.low);
+            common::assert_within_five_sigma(
+                bin.density,
+                bin.theoretical_density,
+                std_error,
+                format!("{:?} bin at {}", config.kernel, bin.low),
+            );
+        }
+        assert!((report.bins.last().unwrap().cdf - 1.0).abs() < 1e-15);
+    });
+}
+
+#[test]
+fn single_points_get_one_histogram() {
+    let config = SimulationConfig {
+        total_simulations: 10_000,
+        num_points: 1,
+        seed: 8,
+        histogram_bins: Some(10),
+        ..SimulationConfig::default()
+    };
+    let run = parallel_simulate(&config).unwrap();
+    let report = Report::new(&config, &run, Duration::from_secs(1));
+    let labels: Vec<&str> = report
+        .histograms
+        .iter()
+        .map(|histogram| histogram.label.as_str())
+        .collect();
+    assert_eq!(labels, ["point"]);
+
+    let two = SimulationConfig {
+        num_points: 2,
+        ..config
+    };
+    let run = parallel_simulate(&two).unwrap();
+    let report = Report::new(&two, &run, Duration::from_secs(1));
+    let labels: Vec<&str> = report
+        .histograms
+        .iter()
+        .map(|histogram| histogram.label.as_str())
+        .collect();
+    assert_eq!(labels, ["minimum", "maximum"]);
+}
@@ -0,0 +1,96 @@
+use montecarlo::{
+    parallel_simulate, replay_trial, Kernel, OutputFormat, Philox4x32, ReplayedTrial, RngKind,
+    SimulationConfig,
+};
+
+// Known-answer vectors of the Random123 reference implementation
+#[test]
+fn matches_reference_implementation() {
+    let vectors = [
+        (
+            [0; 4],
+            [0; 2],
+            [0x6627e8d5, 0xe169c58d, 0xbc57ac4c, 0x9b00dbd8],
+        ),
+        (
+            [0xffffffff; 4],
+            [0xffffffff; 2],
+            [0x408f276d, 0x41c83b0e, 0xa20bc7c6, 0x6d5451fd],
+        ),
+        (
+            [0x243f6a88, 0x85a308d3, 0x13198a2e, 0x03707344],
+            [0xa4093822, 0x299f31d0],
+            [0xd16cfe09, 0x94fdcceb, 0x5001e420, 0x24126ea1],
+        ),
+    ];
+    for (counter, key, output) in vectors {
+        assert_eq!(Philox4x32::block(key, counter), output);
+    }
+}
+
+fn philox_config(block_size: u64, num_threads: u64) -> SimulationConfig {
+    SimulationConfig {
+        total_simulations: 1000,
+        num_threads,
+        num_points: 3,
+        seed: 11,
+        kernel: Kernel::Scalar,
+        rng: Some(RngKind::Philox),
+        block_size,
+        ..SimulationConfig::default()
+    }
+}
+
+#[test]
+fn replayed_trials_match_the_run() {
+    let config = philox_config(1000, 1);
+    let result = parallel_simulate(&config).unwrap().result;
+
+    let mut sums = [0.0; 3];
+    for trial in 0..config.total_simulations {
+        let points = replay_trial(&config, trial).unwrap();
+        let replayed = ReplayedTrial::new(config.seed, trial, points);
+        assert_eq!(replayed.minimum, replayed.sorted[0]);
+        assert_eq!(replayed.maximum, replayed.sorted[2]);
+        for (sum, point) in sums.iter_mut().zip(&replayed.sorted) {
+            *sum += point;
+        }
+    }
+    // A single block adds the trials in the same order, so the sums agree to the last bit
+    for (sum, total) in sums.iter().zip(&result.order_sums) {
+        assert_eq!(*sum, total.value());
+    }
+}
+
+#[test]
+fn trials_do_not_depend_on_blocks_or_threads() {
+    let reference = parallel_simulate(&philox_config(1000, 1)).unwrap().result;
+    for (block_size, num_threads) in [(7, 1), (64, 3)] {
+        let result = parallel_simulate(&philox_config(block_size, num_threads))
+            .unwrap()
+            .result;
+        for (sum, expected) in result.order_sums.iter().zip(&reference.order_sums) {
+            assert!((sum.value() - expected.value()).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn replay_needs_a_counter_based_run() {
+    let config = SimulationConfig {
+        rng: Some(RngKind::Pcg64Mcg),
+        ..philox_config(1000, 1)
+    };
+    assert!(replay_trial(&config, 0).is_err());
+}
+
+#[test]
+fn replayed_trials_list_every_draw_in_csv() {
+    let replayed = ReplayedTrial::new(5, 2, vec![0.5, 0.25, 0.75]);
+    let mut csv = Vec::new();
+    replayed.write(OutputFormat::Csv, &mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "seed,trial,draw,point,k,sorted\n5,2,0,0.5,1,0.25\n5,2,1,0.25,2,0.5\n5,2,2,0.75,3,0.75\n"
+    );
+}
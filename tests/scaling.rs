@@ -0,0 +1,82 @@
+use montecarlo::{
+    available_threads, default_thread_counts, scaling_rows, scaling_study, write_scaling_table,
+    BenchResult, SimulationConfig,
+};
+
+fn result(threads: u64, mean_seconds: f64, stddev_seconds: f64) -> BenchResult {
+    BenchResult {
+        threads,
+        samples: 1_000_000,
+        mean_seconds,
+        stddev_seconds,
+        min_seconds: mean_seconds,
+        max_seconds: mean_seconds,
+        samples_per_second: 1e6 / mean_seconds,
+    }
+}
+
+#[test]
+fn thread_counts_double_up_to_the_limit() {
+    assert_eq!(default_thread_counts(1), [1]);
+    assert_eq!(default_thread_counts(2), [1, 2]);
+    assert_eq!(default_thread_counts(8), [1, 2, 4, 8]);
+    assert_eq!(default_thread_counts(12), [1, 2, 4, 8, 12]);
+    assert_eq!(default_thread_counts(0), [1]);
+}
+
+#[test]
+fn speedups_and_efficiencies_are_over_the_first_row() {
+    let rows = scaling_rows(&[
+        result(2, 4.0, 0.0),
+        result(4, 2.5, 0.0),
+        result(8, 1.0, 0.1),
+    ]);
+    let speedups: Vec<f64> = rows.iter().map(|row| row.speedup).collect();
+    assert_eq!(speedups, [1.0, 1.6, 4.0]);
+    // Doubling the threads at most doubles the speed
+    let efficiencies: Vec<f64> = rows.iter().map(|row| row.efficiency).collect();
+    assert_eq!(efficiencies, [1.0, 0.8, 1.0]);
+    assert_eq!(rows[1].speedup_error, 0.0);
+    assert!((rows[2].speedup_error - 0.4).abs() < 1e-12);
+
+    let mut table = Vec::new();
+    write_scaling_table(&rows, "montecarlo", &mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(
+        lines[0],
+        "| Command | Mean [ms] | Min [ms] | Max [ms] | Speedup | Efficiency |"
+    );
+    assert_eq!(
+        lines[2],
+        "| `montecarlo -t 2` | 4000.0 ± 0.0 | 4000.0 | 4000.0 | 1.00 | 100.0% |"
+    );
+    assert_eq!(
+        lines[4],
+        "| `montecarlo -t 8` | 1000.0 ± 100.0 | 1000.0 | 1000.0 | 4.00 ± 0.40 | 100.0% |"
+    );
+}
+
+#[test]
+fn studies_time_every_thread_count() {
+    let config = SimulationConfig {
+        total_simulations: 10_000,
+        ..SimulationConfig::default()
+    };
+    // What `scaling` runs without --thread-counts, the counts up to `-t auto`
+    let thread_counts = default_thread_counts(available_threads());
+    let rows = scaling_study(&config, &thread_counts, 0, 2).unwrap();
+    let threads: Vec<u64> = rows.iter().map(|row| row.result.threads).collect();
+    assert_eq!(threads, thread_counts);
+    assert_eq!(rows[0].speedup, 1.0);
+    assert_eq!(rows[0].efficiency, 1.0);
+    for row in &rows {
+        assert_eq!(row.result.samples, 10_000);
+        let speedup = rows[0].result.mean_seconds / row.result.mean_seconds;
+        assert_eq!(row.speedup, speedup);
+        assert_eq!(
+            row.efficiency,
+            speedup * thread_counts[0] as f64 / row.result.threads as f64
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use montecarlo::SeedSequence;
+use rand::RngCore;
+use rand_pcg::Pcg64Mcg;
+use std::collections::HashSet;
+
+fn state_128(seeds: &SeedSequence) -> u128 {
+    let mut words = [0u64; 2];
+    seeds.generate_u64(&mut words);
+    u128::from(words[0]) | u128::from(words[1]) << 64
+}
+
+// Reference output of O'Neill's seed_seq_fe, which NumPy's SeedSequence also tests against
+#[test]
+fn matches_reference_implementation() {
+    let words: [u128; 4] = [3735928559, 195939070, 229505742, 305419896];
+    let seeds = SeedSequence::new(
+        words[0] | words[1] << 32 | words[2] << 64 | words[3] << 96,
+        &[],
+    );
+    let mut state = [0u32; 4];
+    seeds.generate_state(&mut state);
+    assert_eq!(state, [3914649087, 576849849, 3593928901, 2229911004]);
+}
+
+#[test]
+fn block_states_do_not_collide() {
+    let mut states = HashSet::new();
+    for master_seed in [0, 1, 2, 42, u128::from(u64::MAX)] {
+        for block in 0..100_000 {
+            assert!(
+                states.insert(state_128(&SeedSequence::new(master_seed, &[block]))),
+                "seed {} block {} repeats an earlier state",
+                master_seed,
+                block
+            );
+        }
+    }
+}
+
+// Seeds used to be master + index * GOLDEN, so shifting the master seed by GOLDEN gave the stream of
+// the neighbouring block
+#[test]
+fn shifted_seeds_do_not_alias_other_blocks() {
+    const GOLDEN: u128 = 0x9e37_79b9_7f4a_7c15;
+    for block in 0..1000 {
+        assert_ne!(
+            state_128(&SeedSequence::new(GOLDEN, &[block])),
+            state_128(&SeedSequence::new(0, &[block + 1]))
+        );
+    }
+    assert_ne!(SeedSequence::new(5, &[]), SeedSequence::new(5, &[0]));
+}
+
+#[test]
+fn streams_do_not_overlap() {
+    let mut outputs = HashSet::new();
+    for block in 0..1000 {
+        let mut rng: Pcg64Mcg = SeedSequence::new(7, &[block]).seed_rng();
+        for _ in 0..1000 {
+            assert!(outputs.insert(rng.next_u64()), "block {} overlaps", block);
+        }
+    }
+}
+
+#[test]
+fn every_seed_bit_reaches_the_state() {
+    let base = state_128(&SeedSequence::new(0x0123_4567_89ab_cdef, &[3]));
+    for bit in 0..128 {
+        let flipped = state_128(&SeedSequence::new(0x0123_4567_89ab_cdef ^ 1 << bit, &[3]));
+        let changed = (base ^ flipped).count_ones();
+        // Half of 128 bits, with plenty of room for chance
+        assert!(
+            (32..=96).contains(&changed),
+            "bit {} changed {}",
+            bit,
+            changed
+        );
+    }
+}
+
+#[test]
+fn neighbouring_streams_are_uncorrelated() {
+    let n = 1_000_000;
+    let mut a: Pcg64Mcg = SeedSequence::new(1, &[0]).seed_rng();
+    let mut b: Pcg64Mcg = SeedSequence::new(1, &[1]).seed_rng();
+    let uniform = |rng: &mut Pcg64Mcg| (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+    let covariance = (0..n)
+        .map(|_| uniform(&mut a) * uniform(&mut b))
+        .sum::<f64>()
+        / n as f64;
+    // Each product has variance 1/144, so the correlation's standard error is 1/sqrt(n)
+    let correlation = covariance * 12.0;
+    assert!(
+        correlation.abs() < 5.0 / (n as f64).sqrt(),
+        "{}",
+        correlation
+    );
+}
@@ -0,0 +1,216 @@
+mod common;
+
+use montecarlo::{
+    parallel_simulate, Accumulation, Distribution, Error, Kernel, Precision, Report, RngKind,
+    SimulationConfig, Statistic,
+};
+use std::time::{Duration, Instant};
+
+#[test]
+fn thread_count_does_not_change_the_sums() {
+    for kernel in common::supported_kernels() {
+        for accumulation in [Accumulation::Naive, Accumulation::Compensated] {
+            let run = |num_threads| {
+                let config = SimulationConfig {
+                    // The last block is a short one
+                    total_simulations: 200_003,
+                    num_threads,
+                    num_points: 3,
+                    seed: 17,
+                    kernel,
+                    statistics: vec![Statistic::Range],
+                    block_size: 10_000,
+                    accumulation,
+                    ..SimulationConfig::default()
+                };
+                parallel_simulate(&config).unwrap().result
+            };
+            let single = run(1);
+            assert_eq!(single.count, 200_003);
+            for num_threads in [3, 8] {
+                let parallel = run(num_threads);
+                assert_eq!(parallel.count, single.count);
+                assert_eq!(
+                    parallel.order_sums, single.order_sums,
+                    "{:?} {:?} on {} threads",
+                    kernel, accumulation, num_threads
+                );
+                assert_eq!(parallel.order_sq_sums, single.order_sq_sums);
+                assert_eq!(parallel.measure_sums, single.measure_sums);
+            }
+        }
+    }
+}
+
+#[test]
+fn every_generator_drives_the_scalar_kernel() {
+    for rng in RngKind::ALL {
+        let config = SimulationConfig {
+            total_simulations: 50_000,
+            num_threads: 2,
+            num_points: 4,
+            seed: 13,
+            kernel: Kernel::Scalar,
+            rng: Some(rng),
+            block_size: 10_000,
+            ..SimulationConfig::default()
+        };
+        let run = parallel_simulate(&config).unwrap();
+        let report = Report::new(&config, &run, Duration::from_secs(1));
+        assert_eq!(RngKind::from_name(report.rng), Some(rng));
+        for stat in &report.statistics {
+            common::assert_within_five_sigma(
+                stat.estimate,
+                stat.theoretical.unwrap(),
+                stat.std_error.unwrap(),
+                format_args!("{} of {}", stat.label, report.rng),
+            );
+        }
+    }
+}
+
+#[test]
+fn single_trials_leave_the_spread_undefined() {
+    let config = SimulationConfig {
+        total_simulations: 1,
+        num_points: 2,
+        seed: 6,
+        ..SimulationConfig::default()
+    };
+    let run = parallel_simulate(&config).unwrap();
+    for estimate in run.result.estimates() {
+        assert!(estimate.mean.is_finite());
+        assert_eq!(estimate.variance, None);
+        assert_eq!(estimate.std_error, None);
+    }
+    let report = Report::new(&config, &run, Duration::from_secs(1));
+    for stat in &report.statistics {
+        assert!(stat.difference.is_some());
+        assert_eq!(stat.z_score, None);
+        assert_eq!(stat.ci95, None);
+    }
+    let json = serde_json::to_value(&report).unwrap();
+    assert!(json["statistics"][0]["std_error"].is_null());
+    let mut csv = Vec::new();
+    report.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let row: Vec<&str> = csv.lines().nth(1).unwrap().split(',').collect();
+    // variance through ci99_high, and z_score
+    assert!(
+        row[17..23].iter().all(|field| field.is_empty()),
+        "{:?}",
+        row
+    );
+    assert_eq!(row[25], "");
+}
+
+#[test]
+fn precision_targets_stop_reproducibly_once_reached() {
+    let config = SimulationConfig {
+        total_simulations: 1 << 30,
+        num_points: 4,
+        seed: 9,
+        precision: Some(Precision::StdError(2e-4)),
+        block_size: 1 << 14,
+        ..SimulationConfig::default()
+    };
+    let run = parallel_simulate(&config).unwrap();
+    let count = run.result.count;
+    assert!(count < config.total_simulations);
+    assert_eq!(count % config.block_size, 0);
+    let estimates = run.result.estimates();
+    assert!(estimates
+        .iter()
+        .all(|estimate| estimate.std_error.unwrap() <= 2e-4));
+    // Just past the target: the rounds at most double the trials run so far
+    let report = Report::new(&config, &run, Duration::from_secs(1));
+    assert!(report.precision.unwrap().reached);
+    let max_std_error = estimates
+        .iter()
+        .map(|estimate| estimate.std_error.unwrap())
+        .fold(0.0, f64::max);
+    assert!(max_std_error > 2e-4 / 2.0);
+
+    // The rounds depend only on the merged estimates, so neither does the stopping point
+    let parallel = parallel_simulate(&SimulationConfig {
+        num_threads: 3,
+        ..config.clone()
+    })
+    .unwrap();
+    assert_eq!(parallel.result.count, count);
+    assert_eq!(parallel.result.order_sums, run.result.order_sums);
+
+    // A cap below the target's trial count stops the run short of it
+    let capped = SimulationConfig {
+        total_simulations: 1 << 15,
+        ..config
+    };
+    let run = parallel_simulate(&capped).unwrap();
+    assert_eq!(run.result.count, 1 << 15);
+    let report = Report::new(&capped, &run, Duration::from_secs(1));
+    assert!(!report.precision.unwrap().reached);
+}
+
+#[test]
+fn time_limits_stop_after_whole_blocks() {
+    let limit = Duration::from_millis(200);
+    let config = SimulationConfig {
+        total_simulations: u64::MAX,
+        num_threads: 2,
+        seed: 4,
+        time_limit: Some(limit),
+        block_size: 1 << 12,
+        ..SimulationConfig::default()
+    };
+    let start = Instant::now();
+    let run = parallel_simulate(&config).unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= limit);
+    // Blocks started before the deadline still finish, but no later ones start
+    assert!(elapsed < limit + Duration::from_secs(2), "{:?}", elapsed);
+    let count = run.result.count;
+    assert!(count > 0);
+    assert_eq!(count % config.block_size, 0);
+    let blocks: u64 = run.workers.iter().map(|worker| worker.blocks).sum();
+    assert_eq!(blocks * config.block_size, count);
+}
+
+#[test]
+fn precision_targets_leave_out_statistics_that_cannot_reach_them() {
+    // The middle of three normal points and the midrange have mean 0
+    let config = SimulationConfig {
+        total_simulations: 1 << 30,
+        num_points: 3,
+        seed: 3,
+        distribution: Distribution::Normal {
+            mean: 0.0,
+            std_dev: 1.0,
+        },
+        statistics: vec![Statistic::Midrange, Statistic::Range],
+        precision: Some(Precision::Relative(1e-3)),
+        block_size: 1 << 16,
+        ..SimulationConfig::default()
+    };
+    assert_eq!(config.precision_targets(), [true, false, true, false, true]);
+    let run = parallel_simulate(&config).unwrap();
+    assert!(run.result.count < config.total_simulations);
+    let report = Report::new(&config, &run, Duration::from_secs(1));
+    let precision = report.precision.unwrap();
+    assert!(precision.reached);
+    assert_eq!(precision.excluded, ["order statistic 2", "midrange"]);
+
+    // Cauchy points have no variance, nor do their extremes a mean
+    let cauchy = SimulationConfig {
+        distribution: Distribution::Cauchy {
+            location: 0.0,
+            scale: 1.0,
+        },
+        statistics: Vec::new(),
+        precision: Some(Precision::StdError(1e-3)),
+        ..config
+    };
+    assert!(matches!(
+        parallel_simulate(&cauchy),
+        Err(Error::InvalidConfig(_))
+    ));
+}
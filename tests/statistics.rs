@@ -0,0 +1,87 @@
+mod common;
+
+use montecarlo::{
+    expected_measure, parallel_simulate, Distribution, Measure, SimulationConfig, Statistic,
+};
+
+const ALL: [Statistic; 5] = [
+    Statistic::Range,
+    Statistic::Midrange,
+    Statistic::Distance,
+    Statistic::Median,
+    Statistic::Gaps,
+];
+
+#[test]
+fn measures_are_the_weighted_sums_they_claim() {
+    let sorted = [0.1, 0.25, 0.3, 0.7, 0.95];
+    for statistic in ALL {
+        for measure in statistic.measures(sorted.len()) {
+            let weighted: f64 = measure
+                .weights(sorted.len())
+                .into_iter()
+                .map(|(i, weight)| weight * sorted[i])
+                .sum();
+            assert!((measure.evaluate(&sorted) - weighted).abs() < 1e-15);
+        }
+    }
+    // Mean of the ten pairwise distances
+    let mut total = 0.0;
+    for i in 0..sorted.len() {
+        for j in 0..i {
+            total += sorted[i] - sorted[j];
+        }
+    }
+    assert!((Measure::Distance.evaluate(&sorted) - total / 10.0).abs() < 1e-15);
+}
+
+#[test]
+fn two_uniform_points_are_a_third_apart() {
+    let uniform = Distribution::default();
+    assert!((expected_measure(&uniform, Measure::Range, 2).unwrap() - 1.0 / 3.0).abs() < 1e-15);
+    assert!((expected_measure(&uniform, Measure::Distance, 5).unwrap() - 1.0 / 3.0).abs() < 1e-15);
+}
+
+#[test]
+fn every_kernel_matches_theory() {
+    let config = SimulationConfig {
+        // Not a multiple of 8, so the SIMD kernels' remainder path is exercised too
+        total_simulations: 200_003,
+        num_points: 5,
+        seed: 9,
+        distribution: Distribution::Exponential { rate: 1.5 },
+        statistics: ALL.to_vec(),
+        ..SimulationConfig::default()
+    };
+    common::on_every_kernel(&config, |config, result| {
+        let measures = config.measures();
+        assert_eq!(result.measures, measures);
+        let estimates = result.estimates();
+        for (measure, estimate) in measures.iter().zip(&estimates[config.num_points..]) {
+            let expected = expected_measure(&config.distribution, *measure, 5).unwrap();
+            common::assert_within_five_sigma(
+                estimate.mean,
+                expected,
+                estimate.std_error.unwrap(),
+                format!("{} kernel, {}", config.kernel.name(), measure.label()),
+            );
+        }
+    });
+}
+
+#[test]
+fn statistics_must_fit_the_point_count() {
+    let config = SimulationConfig {
+        total_simulations: 100,
+        num_points: 4,
+        statistics: vec![Statistic::Median],
+        ..SimulationConfig::default()
+    };
+    assert!(parallel_simulate(&config).is_err());
+    let config = SimulationConfig {
+        num_points: 1,
+        statistics: vec![Statistic::Range],
+        ..config
+    };
+    assert!(parallel_simulate(&config).is_err());
+}
@@ -0,0 +1,211 @@
+use montecarlo::{
+    order_statistic_cdf, order_statistic_moments, parallel_simulate, theory_rows,
+    uniform_order_statistic, write_theory, Distribution, OutputFormat, Report, SimulationConfig,
+};
+use serde_json::json;
+use std::f64::consts::PI;
+use std::time::Duration;
+
+fn assert_close(actual: f64, expected: f64, what: &str) {
+    assert!(
+        (actual - expected).abs() <= 1e-12 * expected.abs().max(1.0),
+        "{}: {} != {}",
+        what,
+        actual,
+        expected
+    );
+}
+
+// Quadrature against the closed forms known for two and three normal points
+#[test]
+fn normal_moments_match_closed_forms() {
+    let normal = Distribution::Normal {
+        mean: 0.0,
+        std_dev: 1.0,
+    };
+    let moments = |k, n| order_statistic_moments(&normal, k, n).unwrap();
+    assert_close(moments(2, 2).mean, 1.0 / PI.sqrt(), "mean of max of 2");
+    assert_close(
+        moments(2, 2).variance,
+        1.0 - 1.0 / PI,
+        "variance of max of 2",
+    );
+    assert_close(moments(3, 3).mean, 1.5 / PI.sqrt(), "mean of max of 3");
+    assert_close(
+        moments(3, 3).variance,
+        (4.0 * PI - 9.0 + 2.0 * 3.0_f64.sqrt()) / (4.0 * PI),
+        "variance of max of 3",
+    );
+    assert_close(moments(2, 3).mean, 0.0, "mean of median of 3");
+    assert_close(
+        moments(2, 3).variance,
+        1.0 - 3.0_f64.sqrt() / PI,
+        "variance of median of 3",
+    );
+}
+
+// Beta(1, 1) is uniform, but goes through the quadrature over its support
+#[test]
+fn beta_quadrature_matches_uniform_moments() {
+    let flat = Distribution::Beta {
+        alpha: 1.0,
+        beta: 1.0,
+    };
+    for k in 1..=5 {
+        let moments = order_statistic_moments(&flat, k, 5).unwrap();
+        let exact = uniform_order_statistic(k, 5);
+        assert_close(moments.mean, exact.mean, "mean");
+        assert_close(moments.variance, exact.variance, "variance");
+    }
+}
+
+#[test]
+fn single_point_moments_match_the_law() {
+    let lognormal = Distribution::LogNormal {
+        mu: 0.0,
+        sigma: 1.0,
+    };
+    let moments = order_statistic_moments(&lognormal, 1, 1).unwrap();
+    assert_close(moments.mean, 0.5_f64.exp(), "lognormal mean");
+    assert_close(
+        moments.variance,
+        (1.0_f64.exp() - 1.0) * 1.0_f64.exp(),
+        "lognormal variance",
+    );
+
+    let triangular = Distribution::Triangular {
+        low: 0.0,
+        mode: 0.25,
+        high: 1.0,
+    };
+    let moments = order_statistic_moments(&triangular, 1, 1).unwrap();
+    assert_close(moments.mean, 1.25 / 3.0, "triangular mean");
+    assert_close(
+        moments.variance,
+        (1.0 + 0.0625 - 0.25) / 18.0,
+        "triangular variance",
+    );
+}
+
+#[test]
+fn cauchy_moments_exist_only_away_from_the_extremes() {
+    let cauchy = Distribution::Cauchy {
+        location: 1.0,
+        scale: 2.0,
+    };
+    assert_eq!(order_statistic_moments(&cauchy, 1, 3), None);
+    assert_eq!(order_statistic_moments(&cauchy, 3, 3), None);
+    let median = order_statistic_moments(&cauchy, 2, 3).unwrap();
+    assert_close(median.mean, 1.0, "median of 3");
+    assert_eq!(median.variance, f64::INFINITY);
+    assert!(order_statistic_moments(&cauchy, 3, 5)
+        .unwrap()
+        .variance
+        .is_finite());
+}
+
+// serde_json writes every non-finite number as null, which would read as unknown
+#[test]
+fn infinite_variances_read_the_same_in_json_and_csv() {
+    let config = SimulationConfig {
+        total_simulations: 1000,
+        num_points: 3,
+        distribution: Distribution::Cauchy {
+            location: 0.0,
+            scale: 1.0,
+        },
+        ..SimulationConfig::default()
+    };
+    let run = parallel_simulate(&config).unwrap();
+    let report = Report::new(&config, &run, Duration::from_secs(1));
+    let json = serde_json::to_value(&report).unwrap();
+    let variances: Vec<_> = json["statistics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|stat| stat["theoretical_variance"].clone())
+        .collect();
+    assert_eq!(variances, [json!(null), json!("inf"), json!(null)]);
+
+    let mut csv = Vec::new();
+    report.write_csv(&mut csv).unwrap();
+    let last_column: Vec<_> = String::from_utf8(csv)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| line.rsplit(',').next().unwrap().to_string())
+        .collect();
+    assert_eq!(last_column, ["", "inf", ""]);
+}
+
+#[test]
+fn theory_tables_leave_missing_moments_empty() {
+    let cauchy = Distribution::Cauchy {
+        location: 0.0,
+        scale: 1.0,
+    };
+    let rows = theory_rows(&cauchy, 3);
+    let labels: Vec<_> = rows.iter().map(|row| row.label.as_str()).collect();
+    assert_eq!(labels, ["minimum", "order statistic 2", "maximum"]);
+    let json = serde_json::to_value(&rows).unwrap();
+    assert_eq!(json[0]["mean"], json!(null));
+    assert_close(json[1]["mean"].as_f64().unwrap(), 0.0, "median of 3");
+    assert_eq!(json[1]["variance"], json!("inf"));
+    assert_eq!(json[1]["std_dev"], json!("inf"));
+
+    let mut csv = Vec::new();
+    write_theory(&rows, OutputFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "points,distribution,k,label,mean,variance,std_dev"
+    );
+    assert_eq!(lines[1], format!("3,\"{}\",1,minimum,,,", cauchy));
+    assert!(lines[2].ends_with(",inf,inf"), "{}", lines[2]);
+}
+
+#[test]
+fn exponential_moments_follow_renyi() {
+    let exponential = Distribution::Exponential { rate: 2.0 };
+    let minimum = order_statistic_moments(&exponential, 1, 4).unwrap();
+    assert_close(minimum.mean, 1.0 / 8.0, "mean of min");
+    assert_close(minimum.variance, 1.0 / 64.0, "variance of min");
+}
+
+#[test]
+fn order_statistic_cdfs() {
+    let uniform = Distribution::default();
+    assert_close(order_statistic_cdf(&uniform, 3, 3, 0.5), 0.125, "max of 3");
+    assert_close(order_statistic_cdf(&uniform, 1, 3, 0.5), 0.875, "min of 3");
+
+    let normal = Distribution::Normal {
+        mean: 0.0,
+        std_dev: 1.0,
+    };
+    assert_close(normal.cdf(1.959_963_984_540_054), 0.975, "normal");
+    assert_close(
+        normal.cdf(-10.0) / 7.619_853_024_160_527e-24,
+        1.0,
+        "normal tail",
+    );
+
+    let beta = Distribution::Beta {
+        alpha: 2.0,
+        beta: 3.0,
+    };
+    // I_x(2, 3) = 6x^2 - 8x^3 + 3x^4
+    assert_close(beta.cdf(0.3), 0.3483, "beta");
+
+    let cauchy = Distribution::Cauchy {
+        location: 1.0,
+        scale: 2.0,
+    };
+    assert_close(cauchy.cdf(3.0), 0.75, "cauchy");
+    // The median of three points is below the location half the time
+    assert_close(
+        order_statistic_cdf(&cauchy, 2, 3, 1.0),
+        0.5,
+        "cauchy median",
+    );
+}